@@ -0,0 +1,315 @@
+//! Throughput of the simulation core, run with `cargo bench`.
+//!
+//! Mature worlds are grown from the default settings and passed through a snapshot. Snapshots in
+//! `benches/snapshots/*.splf` are benchmarked as well, so worlds from real runs can be compared.
+//! Soil and air are updated together by [`diffuse`], which is benchmarked on its own.
+
+#[cfg(feature = "render")]
+use std::hint::black_box;
+use std::{fs, path::Path};
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rand::{Rng, SeedableRng};
+
+use spectaculife::{
+    cells::{
+        life_cell::{
+            genome::{Genome, MutationRate},
+            AliveCell, EnergyDirections, LifeType, OrganismId,
+        },
+        terrain_cell::TerrainCell,
+    },
+    diffusion::diffuse,
+    simulation::Simulation,
+    types::{Settings, SimRng, State, UpdateMode},
+    update::{
+        generate_energy, update_world_buffered, update_world_chunked, update_world_sequential,
+    },
+    world::{Neighbours, WorldNeighbourhood},
+};
+
+const SIZES: [u32; 3] = [64, 128, 256];
+
+/// Steps a world runs before it counts as mature
+const MATURE_STEPS: usize = 300;
+
+fn settings(size: u32) -> Settings {
+    Settings {
+        w: size,
+        h: size,
+        seed: 7,
+        ..Settings::default()
+    }
+}
+
+/// World without any life
+fn empty(size: u32) -> Simulation {
+    Simulation::new(settings(size))
+}
+
+/// Random stem in every cell
+fn dense(size: u32) -> Simulation {
+    let mut simulation = Simulation::new(Settings {
+        seed_spacing: 1,
+        ..settings(size)
+    });
+    simulation.initialize();
+    simulation
+}
+
+/// World that ran for [`MATURE_STEPS`], loaded back from a snapshot
+fn mature(size: u32) -> Simulation {
+    let mut simulation = Simulation::new(settings(size));
+    simulation.initialize();
+
+    for _ in 0..MATURE_STEPS {
+        simulation.step();
+    }
+
+    let mut snapshot = Vec::new();
+    simulation.save_to(&mut snapshot).unwrap();
+    Simulation::load_from(&snapshot[..]).unwrap()
+}
+
+/// Snapshots in `benches/snapshots`, by file name
+fn snapshots() -> Vec<(String, Simulation)> {
+    let Ok(entries) = fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/snapshots"))
+    else {
+        return Vec::new();
+    };
+
+    let mut snapshots: Vec<_> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "splf")
+        })
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            match Simulation::load(&path) {
+                Ok(simulation) => Some((name, simulation)),
+                Err(err) => {
+                    eprintln!("Skipping snapshot {}: {err}", path.display());
+                    None
+                }
+            }
+        })
+        .collect();
+
+    snapshots.sort_by(|a, b| a.0.cmp(&b.0));
+    snapshots
+}
+
+/// Every world benchmarked as a whole, by name
+fn worlds() -> Vec<(String, Simulation)> {
+    let mut worlds = Vec::new();
+
+    for size in SIZES {
+        worlds.push((format!("empty/{size}"), empty(size)));
+        worlds.push((format!("dense/{size}"), dense(size)));
+        worlds.push((format!("mature/{size}"), mature(size)));
+    }
+
+    for (name, simulation) in snapshots() {
+        worlds.push((format!("snapshot/{name}"), simulation));
+    }
+
+    worlds
+}
+
+fn bench_update_world(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_world");
+    group.sample_size(20);
+
+    for (name, simulation) in worlds() {
+        for mode in [
+            UpdateMode::Sequential,
+            UpdateMode::Buffered,
+            UpdateMode::Chunked,
+        ] {
+            let update = match mode {
+                UpdateMode::Sequential => update_world_sequential,
+                UpdateMode::Buffered => update_world_buffered,
+                UpdateMode::Chunked => update_world_chunked,
+            };
+
+            let settings = Settings {
+                update: mode,
+                ..simulation.settings
+            };
+
+            group.bench_with_input(
+                BenchmarkId::new(format!("{mode:?}").to_lowercase(), &name),
+                &simulation,
+                |b, simulation| {
+                    b.iter_batched(
+                        || simulation.clone(),
+                        |mut simulation| {
+                            update(
+                                &settings,
+                                &mut simulation.state,
+                                &mut simulation.rng,
+                                &mut simulation.world,
+                                &mut simulation.genomes,
+                            );
+                            simulation
+                        },
+                        BatchSize::LargeInput,
+                    )
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+fn bench_diffuse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("diffuse");
+
+    for size in SIZES {
+        let simulation = mature(size);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &simulation,
+            |b, simulation| {
+                b.iter_batched(
+                    || simulation.clone(),
+                    |mut simulation| {
+                        diffuse(
+                            &simulation.settings,
+                            &mut simulation.rng,
+                            &mut simulation.world,
+                        );
+                        simulation
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_generate_energy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generate_energy");
+
+    let settings = settings(64);
+    let state = State::default();
+
+    let neighbours = Neighbours {
+        life: Default::default(),
+        organics: [100; 9],
+        soil_energy: [50.; 9],
+        pollution: [40; 9],
+        terrain: [TerrainCell::Open; 9],
+    };
+
+    for ty in [
+        LifeType::Leaf,
+        LifeType::Root,
+        LifeType::Reactor,
+        LifeType::Filter,
+    ] {
+        let life = AliveCell::new(
+            ty,
+            OrganismId(1),
+            10.,
+            EnergyDirections::default(),
+            None,
+            100,
+        );
+
+        group.bench_function(ty.name(), |b| {
+            b.iter_batched(
+                || (neighbours, life),
+                |(mut neighbours, mut life)| {
+                    let mut area = WorldNeighbourhood::from_cells(&mut neighbours, 1, 1);
+                    generate_energy(&settings, &state, &mut area, &mut life);
+                    life
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_mutate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("genome_mutate");
+
+    let mut rng = SimRng::seed_from_u64(7);
+    let genome: Genome = rng.gen();
+
+    for (name, genome) in [
+        ("random_rate", genome),
+        (
+            "full_rate",
+            Genome {
+                mutation_rate: MutationRate(100),
+                ..genome
+            },
+        ),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || genome,
+                |mut genome| {
+                    genome.mutate(&mut rng);
+                    genome
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+#[cfg(feature = "render")]
+fn bench_render_sync(c: &mut Criterion) {
+    use spectaculife::plugins::world::cell_textures;
+
+    let mut group = c.benchmark_group("render_sync");
+
+    for size in SIZES {
+        let simulation = mature(size);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &simulation,
+            |b, simulation| {
+                b.iter(|| {
+                    cell_textures(black_box(simulation)).fold(0u32, |sum, (_, textures)| {
+                        sum.wrapping_add(textures.life ^ textures.energy_directions)
+                    })
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+#[cfg(feature = "render")]
+criterion_group!(
+    benches,
+    bench_update_world,
+    bench_diffuse,
+    bench_generate_energy,
+    bench_mutate,
+    bench_render_sync
+);
+#[cfg(not(feature = "render"))]
+criterion_group!(
+    benches,
+    bench_update_world,
+    bench_diffuse,
+    bench_generate_energy,
+    bench_mutate
+);
+criterion_main!(benches);
@@ -0,0 +1,111 @@
+use crate::{
+    cells::{life_cell::genome::arena::GenomeArena, terrain_cell::TerrainCell, WorldCell},
+    types::Coord,
+    world::World,
+};
+
+/// World layer a brush paints on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrushLayer {
+    Organics,
+    Energy,
+    Pollution,
+    Terrain,
+}
+
+impl BrushLayer {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            BrushLayer::Organics => "Soil organics",
+            BrushLayer::Energy => "Soil energy",
+            BrushLayer::Pollution => "Pollution",
+            BrushLayer::Terrain => "Walls",
+        }
+    }
+
+    /// Layer after this one, `None` after the last one
+    pub const fn next(&self) -> Option<Self> {
+        match self {
+            BrushLayer::Organics => Some(BrushLayer::Energy),
+            BrushLayer::Energy => Some(BrushLayer::Pollution),
+            BrushLayer::Pollution => Some(BrushLayer::Terrain),
+            BrushLayer::Terrain => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Brush {
+    pub layer: BrushLayer,
+    pub radius: u32,
+    pub strength: f32,
+}
+
+impl Brush {
+    /// Add `strength` to the layer in a circle around `center`, or remove it when `erase` is set.
+    /// The amount fades out linearly towards the edge of the circle.
+    /// The terrain layer places walls, clearing everything under them, or removes them.
+    pub fn apply(&self, world: &mut World, genomes: &mut GenomeArena, center: Coord, erase: bool) {
+        let radius = self.radius as i64;
+
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                if distance > radius as f32 {
+                    continue;
+                }
+
+                let amount = self.strength * (1. - distance / (radius + 1) as f32);
+                let Some((x, y)) = world
+                    .life
+                    .resolve(center.x as i64 + dx, center.y as i64 + dy)
+                else {
+                    continue;
+                };
+
+                match self.layer {
+                    BrushLayer::Terrain if erase => world.terrain.uset(x, y, TerrainCell::Open),
+                    BrushLayer::Terrain => {
+                        if let Some(id) = world.life.uget(x, y).genome() {
+                            genomes.release(id);
+                        }
+
+                        world.set_cell(
+                            x,
+                            y,
+                            WorldCell {
+                                terrain: TerrainCell::Wall,
+                                ..WorldCell::default()
+                            },
+                        )
+                    }
+                    _ if world.terrain.uget(x, y).is_wall() => {}
+
+                    BrushLayer::Organics => {
+                        let organics = world.organics.uget_mut(x, y);
+                        *organics = paint_u8(*organics, amount, erase)
+                    }
+                    BrushLayer::Energy if erase => {
+                        let energy = world.soil_energy.uget_mut(x, y);
+                        *energy = (*energy - amount).max(0.)
+                    }
+                    BrushLayer::Energy => *world.soil_energy.uget_mut(x, y) += amount,
+                    BrushLayer::Pollution => {
+                        let pollution = world.pollution.uget_mut(x, y);
+                        *pollution = paint_u8(*pollution, amount, erase)
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn paint_u8(value: u8, amount: f32, erase: bool) -> u8 {
+    let amount = amount.ceil().min(u8::MAX as f32) as u8;
+
+    if erase {
+        value.saturating_sub(amount)
+    } else {
+        value.saturating_add(amount)
+    }
+}
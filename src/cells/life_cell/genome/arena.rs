@@ -0,0 +1,344 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use super::Genome;
+
+/// Index of a genome in the [`GenomeArena`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GenomeId(pub u32);
+
+/// A stored genome and the amount of stem cells using it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    genome: Genome,
+    refs: u32,
+}
+
+/// Genomes of all stem cells, kept out of the cells so the life layer stays small.
+///
+/// Genomes are interned: storing a genome that is already in the arena returns the existing id,
+/// so stems of the same species share one entry. Genomes are never changed once stored, a stem
+/// with a different genome gets a new one.
+///
+/// Stems acquire their genome when they are born or switch to it and release it when they die
+/// or stop using it, a genome is dropped as soon as no stem uses it. Parallel batches can store
+/// the same genome twice, the copies are counted and dropped separately.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<Option<Entry>>", into = "Vec<Option<Entry>>")]
+pub struct GenomeArena {
+    slots: Vec<Option<Entry>>,
+    /// Empty slots in descending order, so the lowest is used first
+    free: Vec<u32>,
+
+    /// Slots by [`Genome::content_hash`]
+    index: HashMap<u64, Vec<u32>>,
+}
+
+impl GenomeArena {
+    /// Panics if the genome was dropped
+    pub fn get(&self, id: GenomeId) -> &Genome {
+        &self.entry(id).genome
+    }
+
+    /// Amount of stem cells using the genome
+    pub fn refs(&self, id: GenomeId) -> u32 {
+        self.entry(id).refs
+    }
+
+    /// Whether `id` refers to a stored genome
+    pub fn contains(&self, id: GenomeId) -> bool {
+        self.slots
+            .get(id.0 as usize)
+            .is_some_and(|entry| entry.is_some())
+    }
+
+    /// Every stored genome with its reference count
+    pub fn iter(&self) -> impl Iterator<Item = (GenomeId, &Genome, u32)> {
+        self.slots.iter().enumerate().filter_map(|(slot, entry)| {
+            entry
+                .as_ref()
+                .map(|entry| (GenomeId(slot as u32), &entry.genome, entry.refs))
+        })
+    }
+
+    /// Id of `genome`, storing it only if it is not in the arena yet.
+    /// The genome is not counted as used until a stem acquires it.
+    pub fn insert(&mut self, genome: Genome) -> GenomeId {
+        let hash = genome.content_hash();
+
+        if let Some(slot) = self.find(hash, &genome) {
+            return GenomeId(slot);
+        }
+
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None => {
+                self.slots.push(None);
+                self.slots.len() as u32 - 1
+            }
+        };
+
+        self.slots[slot as usize] = Some(Entry { genome, refs: 0 });
+        self.index.entry(hash).or_default().push(slot);
+
+        GenomeId(slot)
+    }
+
+    /// Count one more stem using the genome
+    pub fn acquire(&mut self, id: GenomeId) {
+        self.count(id.0, 1);
+    }
+
+    /// Count one stem less using the genome, dropping it when no stem uses it any more
+    pub fn release(&mut self, id: GenomeId) {
+        self.count(id.0, -1);
+    }
+
+    /// Amount of genomes stored
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.free.clear();
+        self.index.clear();
+    }
+
+    /// Split into `parts` batches that can store genomes in parallel, see [`GenomeBatch`]
+    pub fn batches(&self, parts: usize) -> Vec<GenomeBatch<'_>> {
+        (0..parts)
+            .map(|part| {
+                let mut free: Vec<u32> = self
+                    .free
+                    .iter()
+                    .rev()
+                    .skip(part)
+                    .step_by(parts)
+                    .copied()
+                    .collect();
+                free.reverse();
+
+                GenomeBatch {
+                    arena: self,
+
+                    free,
+                    next: (self.slots.len() + part) as u32,
+                    stride: parts as u32,
+
+                    new: HashMap::new(),
+                    index: HashMap::new(),
+                    refs: HashMap::new(),
+                }
+            })
+            .collect()
+    }
+
+    /// Store the genomes and apply the reference counts of batches made by
+    /// [`GenomeArena::batches`]. Genomes the batches stored but no stem acquired are dropped.
+    pub fn append(&mut self, batches: impl IntoIterator<Item = NewGenomes>) {
+        let mut refs: BTreeMap<u32, i64> = BTreeMap::new();
+
+        for batch in batches {
+            let mut genomes: Vec<_> = batch.genomes.into_iter().collect();
+            // Sorted so the arena does not depend on hash map order
+            genomes.sort_by_key(|(slot, _)| *slot);
+
+            for (slot, genome) in &genomes {
+                if *slot as usize >= self.slots.len() {
+                    self.slots.resize(*slot as usize + 1, None);
+                }
+
+                self.index
+                    .entry(genome.content_hash())
+                    .or_default()
+                    .push(*slot);
+                self.slots[*slot as usize] = Some(Entry {
+                    genome: *genome,
+                    refs: 0,
+                });
+
+                refs.entry(*slot).or_default();
+            }
+
+            if !genomes.is_empty() {
+                self.free = self.free_slots();
+            }
+
+            for (slot, change) in batch.refs {
+                *refs.entry(slot).or_default() += change;
+            }
+        }
+
+        for (slot, change) in refs {
+            self.count(slot, change);
+        }
+    }
+
+    fn entry(&self, id: GenomeId) -> &Entry {
+        self.slots[id.0 as usize]
+            .as_ref()
+            .expect("genome dropped while still in use")
+    }
+
+    fn find(&self, hash: u64, genome: &Genome) -> Option<u32> {
+        self.index.get(&hash)?.iter().copied().find(|&slot| {
+            self.slots[slot as usize]
+                .as_ref()
+                .is_some_and(|entry| entry.genome == *genome)
+        })
+    }
+
+    fn free_slots(&self) -> Vec<u32> {
+        (0..self.slots.len() as u32)
+            .rev()
+            .filter(|&slot| self.slots[slot as usize].is_none())
+            .collect()
+    }
+
+    /// Add `change` to the references of `slot`, dropping the genome if none are left
+    fn count(&mut self, slot: u32, change: i64) {
+        let entry = self.slots[slot as usize]
+            .as_mut()
+            .expect("genome dropped while still in use");
+
+        entry.refs = u32::try_from(entry.refs as i64 + change)
+            .expect("genome released by more stems than use it");
+
+        if entry.refs > 0 {
+            return;
+        }
+
+        let hash = entry.genome.content_hash();
+        self.slots[slot as usize] = None;
+
+        if let Some(slots) = self.index.get_mut(&hash) {
+            slots.retain(|&stored| stored != slot);
+
+            if slots.is_empty() {
+                self.index.remove(&hash);
+            }
+        }
+
+        let at = self.free.partition_point(|&free| free > slot);
+        self.free.insert(at, slot);
+    }
+
+    /// Rebuild the free list and the index from the slots
+    fn rebuild(&mut self) {
+        self.free = self.free_slots();
+
+        self.index.clear();
+        for (slot, entry) in self.slots.iter().enumerate() {
+            if let Some(entry) = entry {
+                self.index
+                    .entry(entry.genome.content_hash())
+                    .or_default()
+                    .push(slot as u32);
+            }
+        }
+    }
+}
+
+impl From<Vec<Option<Entry>>> for GenomeArena {
+    fn from(slots: Vec<Option<Entry>>) -> Self {
+        let mut arena = Self {
+            slots,
+            ..Self::default()
+        };
+        arena.rebuild();
+        arena
+    }
+}
+
+impl From<GenomeArena> for Vec<Option<Entry>> {
+    fn from(arena: GenomeArena) -> Self {
+        arena.slots
+    }
+}
+
+/// Genomes stored while the arena is shared by several update tasks.
+///
+/// Every batch fills its own share of the empty slots, then takes new slots interleaved with the
+/// other batches, so batches never collide and slots do not depend on timing.
+pub struct GenomeBatch<'a> {
+    arena: &'a GenomeArena,
+
+    /// Empty slots of this batch in descending order
+    free: Vec<u32>,
+    next: u32,
+    stride: u32,
+
+    new: HashMap<u32, Genome>,
+    /// Slots in `new` by [`Genome::content_hash`]
+    index: HashMap<u64, Vec<u32>>,
+    /// Changes to reference counts, applied when the batch is appended
+    refs: HashMap<u32, i64>,
+}
+
+impl GenomeBatch<'_> {
+    pub fn get(&self, id: GenomeId) -> &Genome {
+        match self.new.get(&id.0) {
+            Some(genome) => genome,
+            None => self.arena.get(id),
+        }
+    }
+
+    /// Id of `genome`, storing it only if neither the arena nor this batch has it yet.
+    /// The genome is dropped on append unless a stem acquires it.
+    pub fn insert(&mut self, genome: Genome) -> GenomeId {
+        let hash = genome.content_hash();
+
+        if let Some(slot) = self.arena.find(hash, &genome) {
+            return GenomeId(slot);
+        }
+
+        let found = self
+            .index
+            .get(&hash)
+            .and_then(|slots| slots.iter().copied().find(|slot| self.new[slot] == genome));
+
+        let slot = found.unwrap_or_else(|| {
+            let slot = self.free.pop().unwrap_or_else(|| {
+                let slot = self.next;
+                self.next += self.stride;
+                slot
+            });
+
+            self.new.insert(slot, genome);
+            self.index.entry(hash).or_default().push(slot);
+            slot
+        });
+
+        GenomeId(slot)
+    }
+
+    /// Count one more stem using the genome
+    pub fn acquire(&mut self, id: GenomeId) {
+        *self.refs.entry(id.0).or_default() += 1;
+    }
+
+    /// Count one stem less using the genome
+    pub fn release(&mut self, id: GenomeId) {
+        *self.refs.entry(id.0).or_default() -= 1;
+    }
+
+    /// Genomes and reference counts to hand to [`GenomeArena::append`]
+    pub fn finish(self) -> NewGenomes {
+        NewGenomes {
+            genomes: self.new,
+            refs: self.refs,
+        }
+    }
+}
+
+/// Genomes stored and references counted by a [`GenomeBatch`]
+pub struct NewGenomes {
+    genomes: HashMap<u32, Genome>,
+    refs: HashMap<u32, i64>,
+}
@@ -0,0 +1,487 @@
+//! Compact byte encoding of a genome.
+//!
+//! Layout: format version, mutation rate, seed gene, active gene, then all 32 genes.
+//! Every enum is one tag byte followed by its arguments, lifespans are little endian `u16`.
+//! Lineage ids are not part of the encoding, they only make sense inside one run.
+
+use std::fmt;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+use super::{
+    Gene, GeneAction, GeneCondition, GeneDirectionAction, GeneLocation, Genome, LifeSpan,
+    LineageId, MutationRate, MAX_GENES,
+};
+
+/// Bumped on every incompatible change of the byte layout
+pub const GENOME_ENCODING_VERSION: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenomeDecodeError {
+    Base64(base64::DecodeError),
+    UnexpectedEnd,
+    TrailingBytes(usize),
+    UnsupportedVersion(u8),
+    InvalidValue { what: &'static str, value: u8 },
+}
+
+impl fmt::Display for GenomeDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Base64(err) => write!(f, "invalid base64: {err}"),
+            Self::UnexpectedEnd => write!(f, "genome data ends unexpectedly"),
+            Self::TrailingBytes(count) => write!(f, "{count} unexpected bytes after genome"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported genome encoding version {version}, expected {GENOME_ENCODING_VERSION}"
+            ),
+            Self::InvalidValue { what, value } => write!(f, "invalid {what} {value}"),
+        }
+    }
+}
+
+impl std::error::Error for GenomeDecodeError {}
+
+impl From<base64::DecodeError> for GenomeDecodeError {
+    fn from(err: base64::DecodeError) -> Self {
+        Self::Base64(err)
+    }
+}
+
+/// Conditions in the order of their tags
+const CONDITIONS: [GeneCondition; 32] = {
+    use GeneCondition::*;
+    [
+        LifeUp,
+        LifeDown,
+        LifeLeft,
+        LifeRight,
+        LethalOrganicUp,
+        LethalOrganicDown,
+        LethalOrganicLeft,
+        LethalOrganicRight,
+        LethalEnergyUp,
+        LethalEnergyDown,
+        LethalEnergyLeft,
+        LethalEnergyRight,
+        RandomMT,
+        LifeEnergyMT,
+        OrganicCenterMT,
+        OrganicUpMT,
+        OrganicDownMT,
+        OrganicLeftMT,
+        OrganicRightMT,
+        SoilEnergyCenterMT,
+        SoilEnergyUpMT,
+        SoilEnergyDownMT,
+        SoilEnergyLeftMT,
+        SoilEnergyRightMT,
+        AirPollutionCenterMT,
+        AirPollutionUpMT,
+        AirPollutionDownMT,
+        AirPollutionLeftMT,
+        AirPollutionRightMT,
+        Always,
+        Never,
+        StepsDividesP,
+    ]
+};
+
+impl Genome {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![
+            GENOME_ENCODING_VERSION,
+            self.mutation_rate.0,
+            self.seed_gene.0,
+            self.active_gene.0,
+        ];
+
+        for gene in &self.genes {
+            encode_gene(gene, &mut out);
+        }
+
+        out
+    }
+
+    /// Decode a genome written by [`Genome::to_bytes`], it starts a fresh lineage
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GenomeDecodeError> {
+        let mut reader = Reader { bytes };
+
+        let version = reader.u8()?;
+        if version != GENOME_ENCODING_VERSION {
+            return Err(GenomeDecodeError::UnsupportedVersion(version));
+        }
+
+        let mutation_rate = match reader.u8()? {
+            rate @ 0..=100 => MutationRate(rate),
+            value => {
+                return Err(GenomeDecodeError::InvalidValue {
+                    what: "mutation rate",
+                    value,
+                })
+            }
+        };
+        let seed_gene = reader.location()?;
+        let active_gene = reader.location()?;
+
+        let mut genes = Vec::with_capacity(MAX_GENES as usize);
+        for _ in 0..MAX_GENES {
+            genes.push(reader.gene()?);
+        }
+
+        if !reader.bytes.is_empty() {
+            return Err(GenomeDecodeError::TrailingBytes(reader.bytes.len()));
+        }
+
+        Ok(Genome {
+            genes: genes.try_into().expect("exactly MAX_GENES genes are read"),
+            active_gene,
+            seed_gene,
+            mutation_rate,
+
+            lineage: LineageId::default(),
+            parent_lineage: None,
+        })
+    }
+
+    /// URL-safe base64 of [`Genome::to_bytes`], for sharing organisms as plain text
+    pub fn to_base64(&self) -> String {
+        URL_SAFE_NO_PAD.encode(self.to_bytes())
+    }
+
+    pub fn from_base64(s: &str) -> Result<Self, GenomeDecodeError> {
+        Self::from_bytes(&URL_SAFE_NO_PAD.decode(s.trim())?)
+    }
+
+    /// Stable hash of the heritable part of the genome, equal across runs and platforms.
+    /// The active gene changes while the organism lives, so it is left out.
+    pub fn content_hash(&self) -> u64 {
+        let heritable = Genome {
+            active_gene: self.seed_gene,
+            ..*self
+        };
+
+        // FNV-1a
+        heritable
+            .to_bytes()
+            .iter()
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+}
+
+fn encode_gene(gene: &Gene, out: &mut Vec<u8>) {
+    for action in [gene.up, gene.down, gene.left, gene.right] {
+        encode_direction_action(action, out);
+    }
+
+    out.extend([
+        condition_tag(gene.main_action_condition),
+        gene.main_action_param,
+    ]);
+    encode_action(gene.main_action, out);
+
+    out.extend([
+        condition_tag(gene.additional_action_condition1),
+        gene.additional_action_param1,
+        condition_tag(gene.additional_action_condition2),
+        gene.additional_action_param2,
+    ]);
+    for action in [
+        gene.additional_action1,
+        gene.additional_action2,
+        gene.additional_action3,
+    ] {
+        encode_action(action, out);
+    }
+
+    out.extend([
+        condition_tag(gene.condition_1),
+        gene.param_1,
+        condition_tag(gene.condition_2),
+        gene.param_2,
+        gene.alt_gene1.0,
+        gene.alt_gene2.0,
+        gene.alt_gene3.0,
+    ]);
+    out.extend(gene.self_lifespan.0.to_le_bytes());
+}
+
+/// Tag of `condition`, its position in [`CONDITIONS`]
+fn condition_tag(condition: GeneCondition) -> u8 {
+    CONDITIONS
+        .iter()
+        .position(|tag| *tag == condition)
+        .expect("every condition has a tag") as u8
+}
+
+fn encode_direction_action(action: GeneDirectionAction, out: &mut Vec<u8>) {
+    use GeneDirectionAction::*;
+    match action {
+        MakeLeaf(lifespan) => encode_lifespan(0, lifespan, out),
+        MakeRoot(lifespan) => encode_lifespan(1, lifespan, out),
+        MakeReactor(lifespan) => encode_lifespan(2, lifespan, out),
+        MakeFilter(lifespan) => encode_lifespan(3, lifespan, out),
+        MultiplySelf(lifespan, gene) => {
+            encode_lifespan(4, lifespan, out);
+            out.push(gene.0);
+        }
+        KillCell => out.push(5),
+        CreateSeed(lifespan) => encode_lifespan(6, lifespan, out),
+        Nothing => out.push(7),
+    }
+}
+
+fn encode_lifespan(tag: u8, lifespan: LifeSpan, out: &mut Vec<u8>) {
+    out.push(tag);
+    out.extend(lifespan.0.to_le_bytes());
+}
+
+fn encode_action(action: GeneAction, out: &mut Vec<u8>) {
+    use GeneAction::*;
+    let tag = match action {
+        MoveOrganicUp => 0,
+        MoveOrganicDown => 1,
+        MoveOrganicLeft => 2,
+        MoveOrganicRight => 3,
+
+        MoveOrganicFromUp => 4,
+        MoveOrganicFromDown => 5,
+        MoveOrganicFromLeft => 6,
+        MoveOrganicFromRight => 7,
+
+        DoNothing => 8,
+
+        ChangeActiveGene(gene) => {
+            out.extend([9, gene.0]);
+            return;
+        }
+
+        KillUpLeft => 10,
+        KillUpRight => 11,
+        KillDownLeft => 12,
+        KillDownRight => 13,
+
+        WaitStep => 14,
+        Die => 15,
+    };
+
+    out.push(tag);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn u8(&mut self) -> Result<u8, GenomeDecodeError> {
+        let (&byte, rest) = self
+            .bytes
+            .split_first()
+            .ok_or(GenomeDecodeError::UnexpectedEnd)?;
+        self.bytes = rest;
+        Ok(byte)
+    }
+
+    fn lifespan(&mut self) -> Result<LifeSpan, GenomeDecodeError> {
+        Ok(LifeSpan(u16::from_le_bytes([self.u8()?, self.u8()?])))
+    }
+
+    fn location(&mut self) -> Result<GeneLocation, GenomeDecodeError> {
+        match self.u8()? {
+            location if location < MAX_GENES => Ok(GeneLocation(location)),
+            value => Err(GenomeDecodeError::InvalidValue {
+                what: "gene location",
+                value,
+            }),
+        }
+    }
+
+    fn condition(&mut self) -> Result<GeneCondition, GenomeDecodeError> {
+        let value = self.u8()?;
+        CONDITIONS
+            .get(value as usize)
+            .copied()
+            .ok_or(GenomeDecodeError::InvalidValue {
+                what: "condition",
+                value,
+            })
+    }
+
+    fn direction_action(&mut self) -> Result<GeneDirectionAction, GenomeDecodeError> {
+        use GeneDirectionAction::*;
+        match self.u8()? {
+            0 => Ok(MakeLeaf(self.lifespan()?)),
+            1 => Ok(MakeRoot(self.lifespan()?)),
+            2 => Ok(MakeReactor(self.lifespan()?)),
+            3 => Ok(MakeFilter(self.lifespan()?)),
+            4 => Ok(MultiplySelf(self.lifespan()?, self.location()?)),
+            5 => Ok(KillCell),
+            6 => Ok(CreateSeed(self.lifespan()?)),
+            7 => Ok(Nothing),
+            value => Err(GenomeDecodeError::InvalidValue {
+                what: "direction action",
+                value,
+            }),
+        }
+    }
+
+    fn action(&mut self) -> Result<GeneAction, GenomeDecodeError> {
+        use GeneAction::*;
+        match self.u8()? {
+            0 => Ok(MoveOrganicUp),
+            1 => Ok(MoveOrganicDown),
+            2 => Ok(MoveOrganicLeft),
+            3 => Ok(MoveOrganicRight),
+
+            4 => Ok(MoveOrganicFromUp),
+            5 => Ok(MoveOrganicFromDown),
+            6 => Ok(MoveOrganicFromLeft),
+            7 => Ok(MoveOrganicFromRight),
+
+            8 => Ok(DoNothing),
+
+            9 => Ok(ChangeActiveGene(self.location()?)),
+
+            10 => Ok(KillUpLeft),
+            11 => Ok(KillUpRight),
+            12 => Ok(KillDownLeft),
+            13 => Ok(KillDownRight),
+
+            14 => Ok(WaitStep),
+            15 => Ok(Die),
+            value => Err(GenomeDecodeError::InvalidValue {
+                what: "action",
+                value,
+            }),
+        }
+    }
+
+    fn gene(&mut self) -> Result<Gene, GenomeDecodeError> {
+        Ok(Gene {
+            up: self.direction_action()?,
+            down: self.direction_action()?,
+            left: self.direction_action()?,
+            right: self.direction_action()?,
+
+            main_action_condition: self.condition()?,
+            main_action_param: self.u8()?,
+            main_action: self.action()?,
+
+            additional_action_condition1: self.condition()?,
+            additional_action_param1: self.u8()?,
+
+            additional_action_condition2: self.condition()?,
+            additional_action_param2: self.u8()?,
+
+            additional_action1: self.action()?,
+            additional_action2: self.action()?,
+            additional_action3: self.action()?,
+
+            condition_1: self.condition()?,
+            param_1: self.u8()?,
+
+            condition_2: self.condition()?,
+            param_2: self.u8()?,
+
+            alt_gene1: self.location()?,
+            alt_gene2: self.location()?,
+            alt_gene3: self.location()?,
+
+            self_lifespan: self.lifespan()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+
+    use crate::types::SimRng;
+
+    use super::*;
+
+    /// Random genome as a decoder returns it, without lineage
+    fn genome(rng: &mut SimRng) -> Genome {
+        Genome {
+            lineage: LineageId::default(),
+            parent_lineage: None,
+            ..rng.gen()
+        }
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut rng = SimRng::seed_from_u64(7);
+
+        for _ in 0..100 {
+            let genome = genome(&mut rng);
+            assert_eq!(Genome::from_bytes(&genome.to_bytes()), Ok(genome));
+        }
+    }
+
+    #[test]
+    fn base64_round_trip() {
+        let mut rng = SimRng::seed_from_u64(7);
+
+        for _ in 0..100 {
+            let genome = genome(&mut rng);
+            assert_eq!(Genome::from_base64(&genome.to_base64()), Ok(genome));
+        }
+    }
+
+    #[test]
+    fn content_hash_ignores_lineage_and_active_gene() {
+        let mut rng = SimRng::seed_from_u64(7);
+        let genome = genome(&mut rng);
+
+        let descendant = Genome {
+            lineage: LineageId(42),
+            parent_lineage: Some(LineageId(7)),
+            active_gene: GeneLocation((genome.active_gene.0 + 1) % MAX_GENES),
+            ..genome
+        };
+        assert_eq!(descendant.content_hash(), genome.content_hash());
+
+        let mutant = Genome {
+            mutation_rate: MutationRate((genome.mutation_rate.0 + 1) % 101),
+            ..genome
+        };
+        assert_ne!(mutant.content_hash(), genome.content_hash());
+    }
+
+    #[test]
+    fn every_condition_round_trips() {
+        let mut rng = SimRng::seed_from_u64(7);
+        let mut genome = genome(&mut rng);
+
+        for condition in CONDITIONS {
+            genome.genes[0].main_action_condition = condition;
+            genome.genes[0].condition_2 = condition;
+
+            assert_eq!(Genome::from_bytes(&genome.to_bytes()), Ok(genome));
+        }
+    }
+
+    #[test]
+    fn rejects_bad_data() {
+        let mut rng = SimRng::seed_from_u64(7);
+        let bytes = genome(&mut rng).to_bytes();
+
+        assert_eq!(
+            Genome::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(GenomeDecodeError::UnexpectedEnd)
+        );
+        assert_eq!(
+            Genome::from_bytes(&[bytes.as_slice(), &[0]].concat()),
+            Err(GenomeDecodeError::TrailingBytes(1))
+        );
+        assert_eq!(
+            Genome::from_bytes(&[GENOME_ENCODING_VERSION + 1]),
+            Err(GenomeDecodeError::UnsupportedVersion(
+                GENOME_ENCODING_VERSION + 1
+            ))
+        );
+    }
+}
@@ -1,11 +1,16 @@
 use rand::{
     distributions::{Distribution, Standard},
-    thread_rng, Rng,
+    Rng,
 };
+use serde::{Deserialize, Serialize};
+
+pub mod arena;
+pub mod encoding;
+pub mod text;
 
 pub const MAX_GENES: u8 = 32;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct MutationRate(pub u8);
 
 impl Distribution<MutationRate> for Standard {
@@ -14,7 +19,7 @@ impl Distribution<MutationRate> for Standard {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct GeneLocation(pub u8);
 
 impl Distribution<GeneLocation> for Standard {
@@ -23,7 +28,7 @@ impl Distribution<GeneLocation> for Standard {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct LifeSpan(pub u16);
 
 impl Distribution<LifeSpan> for Standard {
@@ -32,12 +37,21 @@ impl Distribution<LifeSpan> for Standard {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Identifies a line of identical genomes, a new one starts on every mutation
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+pub struct LineageId(pub u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Genome {
     pub genes: [Gene; MAX_GENES as usize],
     pub active_gene: GeneLocation,
     pub seed_gene: GeneLocation,
     pub mutation_rate: MutationRate,
+
+    pub lineage: LineageId,
+    pub parent_lineage: Option<LineageId>,
 }
 
 impl Genome {
@@ -49,8 +63,16 @@ impl Genome {
         self.genes[loc.0 as usize]
     }
 
-    pub fn mutate(&mut self) {
-        let mut rng = thread_rng();
+    /// Start a new lineage descending from the current one
+    pub fn branch_lineage(&mut self, lineage: LineageId) {
+        self.parent_lineage = Some(self.lineage);
+        self.lineage = lineage;
+    }
+
+    /// Randomly mutate genome, returns `true` if the heritable part of it changed.
+    /// A mutation can pick a value equal to the old one or only switch the active gene.
+    pub fn mutate<R: Rng + ?Sized>(&mut self, rng: &mut R) -> bool {
+        let before = *self;
 
         if rng.gen_ratio(self.mutation_rate.0 as u32, 100) {
             match rng.gen_range(0..=11) {
@@ -120,6 +142,12 @@ impl Genome {
                 }
             }
         }
+
+        *self
+            != Genome {
+                active_gene: self.active_gene,
+                ..before
+            }
     }
 }
 
@@ -131,11 +159,14 @@ impl Distribution<Genome> for Standard {
             seed_gene,
             genes: rng.gen(),
             mutation_rate: rng.gen(),
+
+            lineage: LineageId::default(),
+            parent_lineage: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Gene {
     pub up: GeneDirectionAction,
     pub down: GeneDirectionAction,
@@ -215,7 +246,7 @@ impl Distribution<Gene> for Standard {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum GeneDirectionAction {
     MakeLeaf(LifeSpan),
     MakeRoot(LifeSpan),
@@ -259,7 +290,7 @@ impl Distribution<GeneDirectionAction> for Standard {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum GeneCondition {
     LifeUp,
     LifeDown,
@@ -351,7 +382,7 @@ impl Distribution<GeneCondition> for Standard {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum GeneAction {
     MoveOrganicUp,
     MoveOrganicDown,
@@ -405,3 +436,25 @@ impl Distribution<GeneAction> for Standard {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use crate::types::SimRng;
+
+    use super::*;
+
+    #[test]
+    fn mutate_reports_heritable_changes() {
+        let mut rng = SimRng::seed_from_u64(7);
+
+        for _ in 0..1000 {
+            let mut genome: Genome = rng.gen();
+            let before = genome.content_hash();
+
+            let mutated = genome.mutate(&mut rng);
+            assert_eq!(mutated, genome.content_hash() != before);
+        }
+    }
+}
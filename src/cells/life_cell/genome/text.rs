@@ -0,0 +1,515 @@
+//! Human-readable genome format.
+//!
+//! ```text
+//! mutation_rate=42
+//! seed_gene=3
+//! active_gene=3
+//! gene 0 up=MakeLeaf(120) down=Nothing left=KillCell right=MultiplySelf(300,5) main=DoNothing main_if=Always(0) extra=MoveOrganicUp,WaitStep,ChangeActiveGene(4) extra_if=RandomMT(100),LifeUp(0) alt=1,2,3 alt_if=LifeEnergyMT(10),Never(0) lifespan=500
+//! ...
+//! ```
+//!
+//! Every one of the 32 genes must be listed once, empty lines and `#` comments are ignored.
+
+use std::{error::Error, fmt, str::FromStr};
+
+use super::{
+    Gene, GeneAction, GeneCondition, GeneDirectionAction, GeneLocation, Genome, LifeSpan,
+    LineageId, MutationRate, MAX_GENES,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenomeParseError {
+    /// 1-based line number, `0` for errors about the whole genome
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for GenomeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "line {}: {}", self.line, self.message)
+        }
+    }
+}
+
+impl Error for GenomeParseError {}
+
+impl fmt::Display for Genome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "mutation_rate={}", self.mutation_rate.0)?;
+        writeln!(f, "seed_gene={}", self.seed_gene.0)?;
+        writeln!(f, "active_gene={}", self.active_gene.0)?;
+
+        for (i, gene) in self.genes.iter().enumerate() {
+            writeln!(f, "gene {i} {gene}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Genome {
+    type Err = GenomeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mutation_rate = None;
+        let mut seed_gene = None;
+        let mut active_gene = None;
+        let mut genes: [Option<Gene>; MAX_GENES as usize] = [None; MAX_GENES as usize];
+
+        for (i, line) in s.lines().enumerate() {
+            let error = |message: String| GenomeParseError {
+                line: i + 1,
+                message,
+            };
+
+            let line = line.split('#').next().unwrap_or_default().trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(gene) = line.strip_prefix("gene ") {
+                let (index, gene) = gene.trim().split_once(' ').unwrap_or((gene, ""));
+                let index = parse_location(index).map_err(error)?;
+                let slot = &mut genes[index.0 as usize];
+
+                if slot.is_some() {
+                    return Err(error(format!("gene {} is listed twice", index.0)));
+                }
+
+                *slot = Some(gene.parse().map_err(error)?);
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("expected `key=value` or `gene`, got `{line}`")))?;
+
+            match key.trim() {
+                "mutation_rate" => mutation_rate = Some(parse_mutation_rate(value).map_err(error)?),
+                "seed_gene" => seed_gene = Some(parse_location(value).map_err(error)?),
+                "active_gene" => active_gene = Some(parse_location(value).map_err(error)?),
+                key => return Err(error(format!("unknown key `{key}`"))),
+            }
+        }
+
+        let missing = |what: &str| GenomeParseError {
+            line: 0,
+            message: format!("missing {what}"),
+        };
+
+        if let Some(i) = genes.iter().position(Option::is_none) {
+            return Err(missing(&format!("gene {i}")));
+        }
+
+        let seed_gene = seed_gene.ok_or_else(|| missing("seed_gene"))?;
+
+        Ok(Genome {
+            genes: genes.map(Option::unwrap),
+            active_gene: active_gene.unwrap_or(seed_gene),
+            seed_gene,
+            mutation_rate: mutation_rate.ok_or_else(|| missing("mutation_rate"))?,
+
+            lineage: LineageId::default(),
+            parent_lineage: None,
+        })
+    }
+}
+
+impl fmt::Display for Gene {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "up={} down={} left={} right={} \
+             main={} main_if={}({}) \
+             extra={},{},{} extra_if={}({}),{}({}) \
+             alt={},{},{} alt_if={}({}),{}({}) \
+             lifespan={}",
+            self.up,
+            self.down,
+            self.left,
+            self.right,
+            self.main_action,
+            self.main_action_condition,
+            self.main_action_param,
+            self.additional_action1,
+            self.additional_action2,
+            self.additional_action3,
+            self.additional_action_condition1,
+            self.additional_action_param1,
+            self.additional_action_condition2,
+            self.additional_action_param2,
+            self.alt_gene1.0,
+            self.alt_gene2.0,
+            self.alt_gene3.0,
+            self.condition_1,
+            self.param_1,
+            self.condition_2,
+            self.param_2,
+            self.self_lifespan.0,
+        )
+    }
+}
+
+impl FromStr for Gene {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = std::collections::HashMap::new();
+
+        for token in s.split_whitespace() {
+            let (key, value) = token
+                .split_once('=')
+                .ok_or_else(|| format!("expected `key=value`, got `{token}`"))?;
+
+            if fields.insert(key, value).is_some() {
+                return Err(format!("`{key}` is listed twice"));
+            }
+        }
+
+        let mut field = |key: &str| fields.remove(key).ok_or_else(|| format!("missing `{key}`"));
+
+        let (up, down, left, right) = (
+            field("up")?.parse()?,
+            field("down")?.parse()?,
+            field("left")?.parse()?,
+            field("right")?.parse()?,
+        );
+
+        let main_action = field("main")?.parse()?;
+        let (main_action_condition, main_action_param) = parse_condition(field("main_if")?)?;
+
+        let [additional_action1, additional_action2, additional_action3] =
+            parse_list(field("extra")?, |action| action.parse())?;
+        let [(additional_action_condition1, additional_action_param1), (additional_action_condition2, additional_action_param2)] =
+            parse_list(field("extra_if")?, parse_condition)?;
+
+        let [alt_gene1, alt_gene2, alt_gene3] = parse_list(field("alt")?, parse_location)?;
+        let [(condition_1, param_1), (condition_2, param_2)] =
+            parse_list(field("alt_if")?, parse_condition)?;
+
+        let self_lifespan = LifeSpan(parse_number(field("lifespan")?)?);
+
+        if let Some(key) = fields.keys().next() {
+            return Err(format!("unknown key `{key}`"));
+        }
+
+        Ok(Gene {
+            up,
+            down,
+            left,
+            right,
+
+            main_action_condition,
+            main_action_param,
+            main_action,
+
+            additional_action_condition1,
+            additional_action_param1,
+
+            additional_action_condition2,
+            additional_action_param2,
+
+            additional_action1,
+            additional_action2,
+            additional_action3,
+
+            condition_1,
+            param_1,
+
+            condition_2,
+            param_2,
+
+            alt_gene1,
+            alt_gene2,
+            alt_gene3,
+
+            self_lifespan,
+        })
+    }
+}
+
+impl fmt::Display for GeneDirectionAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use GeneDirectionAction::*;
+        match self {
+            MakeLeaf(lifespan) => write!(f, "MakeLeaf({})", lifespan.0),
+            MakeRoot(lifespan) => write!(f, "MakeRoot({})", lifespan.0),
+            MakeReactor(lifespan) => write!(f, "MakeReactor({})", lifespan.0),
+            MakeFilter(lifespan) => write!(f, "MakeFilter({})", lifespan.0),
+            MultiplySelf(lifespan, gene) => write!(f, "MultiplySelf({},{})", lifespan.0, gene.0),
+            KillCell => write!(f, "KillCell"),
+            CreateSeed(lifespan) => write!(f, "CreateSeed({})", lifespan.0),
+            Nothing => write!(f, "Nothing"),
+        }
+    }
+}
+
+impl FromStr for GeneDirectionAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use GeneDirectionAction::*;
+
+        let (name, args) = parse_call(s)?;
+        let lifespan = |args: &[&str]| Ok::<_, String>(LifeSpan(parse_number(args[0])?));
+
+        match (name, args.as_slice()) {
+            ("MakeLeaf", args @ [_]) => Ok(MakeLeaf(lifespan(args)?)),
+            ("MakeRoot", args @ [_]) => Ok(MakeRoot(lifespan(args)?)),
+            ("MakeReactor", args @ [_]) => Ok(MakeReactor(lifespan(args)?)),
+            ("MakeFilter", args @ [_]) => Ok(MakeFilter(lifespan(args)?)),
+            ("MultiplySelf", args @ [_, gene]) => {
+                Ok(MultiplySelf(lifespan(args)?, parse_location(gene)?))
+            }
+            ("KillCell", []) => Ok(KillCell),
+            ("CreateSeed", args @ [_]) => Ok(CreateSeed(lifespan(args)?)),
+            ("Nothing", []) => Ok(Nothing),
+            _ => Err(format!("invalid direction action `{s}`")),
+        }
+    }
+}
+
+/// Implements `Display` and `FromStr` for enums of unit variants using variant names
+macro_rules! named_variants {
+    ($ty: ident, $what: literal, [$($variant: ident),* $(,)?]) => {
+        impl fmt::Display for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    $($ty::$variant => write!(f, stringify!($variant)),)*
+                }
+            }
+        }
+
+        impl FromStr for $ty {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $(stringify!($variant) => Ok($ty::$variant),)*
+                    _ => Err(format!(concat!("invalid ", $what, " `{}`"), s)),
+                }
+            }
+        }
+    };
+}
+
+named_variants!(
+    GeneCondition,
+    "condition",
+    [
+        LifeUp,
+        LifeDown,
+        LifeLeft,
+        LifeRight,
+        LethalOrganicUp,
+        LethalOrganicDown,
+        LethalOrganicLeft,
+        LethalOrganicRight,
+        LethalEnergyUp,
+        LethalEnergyDown,
+        LethalEnergyLeft,
+        LethalEnergyRight,
+        RandomMT,
+        LifeEnergyMT,
+        OrganicCenterMT,
+        OrganicUpMT,
+        OrganicDownMT,
+        OrganicLeftMT,
+        OrganicRightMT,
+        SoilEnergyCenterMT,
+        SoilEnergyUpMT,
+        SoilEnergyDownMT,
+        SoilEnergyLeftMT,
+        SoilEnergyRightMT,
+        AirPollutionCenterMT,
+        AirPollutionUpMT,
+        AirPollutionDownMT,
+        AirPollutionLeftMT,
+        AirPollutionRightMT,
+        Always,
+        Never,
+        StepsDividesP,
+    ]
+);
+
+impl fmt::Display for GeneAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use GeneAction::*;
+        match self {
+            MoveOrganicUp => write!(f, "MoveOrganicUp"),
+            MoveOrganicDown => write!(f, "MoveOrganicDown"),
+            MoveOrganicLeft => write!(f, "MoveOrganicLeft"),
+            MoveOrganicRight => write!(f, "MoveOrganicRight"),
+
+            MoveOrganicFromUp => write!(f, "MoveOrganicFromUp"),
+            MoveOrganicFromDown => write!(f, "MoveOrganicFromDown"),
+            MoveOrganicFromLeft => write!(f, "MoveOrganicFromLeft"),
+            MoveOrganicFromRight => write!(f, "MoveOrganicFromRight"),
+
+            DoNothing => write!(f, "DoNothing"),
+
+            ChangeActiveGene(gene) => write!(f, "ChangeActiveGene({})", gene.0),
+
+            KillUpLeft => write!(f, "KillUpLeft"),
+            KillUpRight => write!(f, "KillUpRight"),
+            KillDownLeft => write!(f, "KillDownLeft"),
+            KillDownRight => write!(f, "KillDownRight"),
+
+            WaitStep => write!(f, "WaitStep"),
+            Die => write!(f, "Die"),
+        }
+    }
+}
+
+impl FromStr for GeneAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use GeneAction::*;
+
+        let (name, args) = parse_call(s)?;
+
+        match (name, args.as_slice()) {
+            ("MoveOrganicUp", []) => Ok(MoveOrganicUp),
+            ("MoveOrganicDown", []) => Ok(MoveOrganicDown),
+            ("MoveOrganicLeft", []) => Ok(MoveOrganicLeft),
+            ("MoveOrganicRight", []) => Ok(MoveOrganicRight),
+
+            ("MoveOrganicFromUp", []) => Ok(MoveOrganicFromUp),
+            ("MoveOrganicFromDown", []) => Ok(MoveOrganicFromDown),
+            ("MoveOrganicFromLeft", []) => Ok(MoveOrganicFromLeft),
+            ("MoveOrganicFromRight", []) => Ok(MoveOrganicFromRight),
+
+            ("DoNothing", []) => Ok(DoNothing),
+
+            ("ChangeActiveGene", [gene]) => Ok(ChangeActiveGene(parse_location(gene)?)),
+
+            ("KillUpLeft", []) => Ok(KillUpLeft),
+            ("KillUpRight", []) => Ok(KillUpRight),
+            ("KillDownLeft", []) => Ok(KillDownLeft),
+            ("KillDownRight", []) => Ok(KillDownRight),
+
+            ("WaitStep", []) => Ok(WaitStep),
+            ("Die", []) => Ok(Die),
+            _ => Err(format!("invalid action `{s}`")),
+        }
+    }
+}
+
+/// Split `Name(a,b)` into `Name` and its arguments
+fn parse_call(s: &str) -> Result<(&str, Vec<&str>), String> {
+    let Some((name, args)) = s.split_once('(') else {
+        return Ok((s, Vec::new()));
+    };
+
+    let args = args
+        .strip_suffix(')')
+        .ok_or_else(|| format!("missing `)` in `{s}`"))?;
+
+    Ok((name, args.split(',').map(str::trim).collect()))
+}
+
+/// Parse `Condition(param)`
+fn parse_condition(s: &str) -> Result<(GeneCondition, u8), String> {
+    match parse_call(s)? {
+        (name, args) if args.len() == 1 => Ok((name.parse()?, parse_number(args[0])?)),
+        _ => Err(format!("expected `Condition(param)`, got `{s}`")),
+    }
+}
+
+/// Parse exactly `N` comma separated items, commas inside parentheses are not separators
+fn parse_list<T, const N: usize>(
+    s: &str,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<[T; N], String> {
+    let mut items = Vec::with_capacity(N);
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(parse(&s[start..i])?);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    items.push(parse(&s[start..])?);
+
+    let len = items.len();
+    items
+        .try_into()
+        .map_err(|_| format!("expected {N} items, got {len} in `{s}`"))
+}
+
+fn parse_number<T: FromStr>(s: &str) -> Result<T, String> {
+    s.trim()
+        .parse()
+        .map_err(|_| format!("invalid number `{s}`"))
+}
+
+fn parse_location(s: &str) -> Result<GeneLocation, String> {
+    let location: u8 = parse_number(s)?;
+
+    if location >= MAX_GENES {
+        return Err(format!("gene {location} is out of range 0..{MAX_GENES}"));
+    }
+
+    Ok(GeneLocation(location))
+}
+
+fn parse_mutation_rate(s: &str) -> Result<MutationRate, String> {
+    let rate: u8 = parse_number(s)?;
+
+    if rate > 100 {
+        return Err(format!("mutation rate {rate} is above 100"));
+    }
+
+    Ok(MutationRate(rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+
+    use crate::types::SimRng;
+
+    use super::*;
+
+    #[test]
+    fn text_round_trip() {
+        let mut rng = SimRng::seed_from_u64(7);
+
+        for _ in 0..100 {
+            let genome = Genome {
+                lineage: LineageId::default(),
+                parent_lineage: None,
+                ..rng.gen()
+            };
+
+            assert_eq!(genome.to_string().parse::<Genome>().unwrap(), genome);
+        }
+    }
+
+    #[test]
+    fn rejects_missing_genes() {
+        let mut rng = SimRng::seed_from_u64(7);
+        let text = rng.gen::<Genome>().to_string();
+        let text: String = text
+            .lines()
+            .filter(|line| !line.starts_with("gene 5 "))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let err = text.parse::<Genome>().unwrap_err();
+        assert_eq!(err.message, "missing gene 5");
+    }
+}
@@ -1,16 +1,18 @@
-use genome::Genome;
+use genome::arena::GenomeId;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    grid::Area,
-    types::CellDir::{self, *},
+    grid::{BoundaryCell, Grid},
+    types::{
+        BoundaryMode,
+        CellDir::{self, *},
+    },
     utils::merge_energy,
 };
 
-use super::WorldCell;
-
 pub mod genome;
 
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub enum LifeCell {
     Alive(AliveCell),
 
@@ -19,9 +21,9 @@ pub enum LifeCell {
 }
 
 impl LifeCell {
-    pub const fn texture_id(&self, area: &Area<WorldCell>) -> u32 {
+    pub fn texture_id(&self, life: &Grid<LifeCell>, x: u32, y: u32) -> u32 {
         match self {
-            Self::Alive(alive_life_cell) => alive_life_cell.texture_id(area),
+            Self::Alive(alive_life_cell) => alive_life_cell.texture_id(life, x, y),
             Self::Dead => 16,
         }
     }
@@ -88,11 +90,34 @@ impl LifeCell {
             Self::Dead => 0,
         }
     }
+
+    /// Genome of a stem cell
+    pub const fn genome(&self) -> Option<GenomeId> {
+        match self {
+            Self::Alive(AliveCell {
+                ty: LifeType::Stem(id),
+                ..
+            }) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+impl BoundaryCell for LifeCell {
+    /// Nothing lives beyond the edge
+    fn outside(_: BoundaryMode, _: &Self) -> Self {
+        Self::Dead
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Identifies all cells grown from the same seed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct OrganismId(pub u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct AliveCell {
     pub ty: LifeType,
+    pub organism: OrganismId,
 
     pub energy: f32,
     pub energy_to: EnergyDirections,
@@ -105,6 +130,7 @@ pub struct AliveCell {
 impl AliveCell {
     pub const fn new(
         ty: LifeType,
+        organism: OrganismId,
         energy: f32,
         energy_to: EnergyDirections,
         parent_dir: Option<CellDir>,
@@ -112,6 +138,7 @@ impl AliveCell {
     ) -> Self {
         Self {
             ty,
+            organism,
 
             energy,
             energy_to,
@@ -143,9 +170,9 @@ impl AliveCell {
         }
     }
 
-    pub const fn texture_id(&self, area: &Area<WorldCell>) -> u32 {
+    pub fn texture_id(&self, life: &Grid<LifeCell>, x: u32, y: u32) -> u32 {
         match self.ty {
-            LifeType::Pipe => match merge_energy(&area, self.energy_to).to_tuple() {
+            LifeType::Pipe => match merge_energy(life, x, y, self.energy_to).to_tuple() {
                 (false, false, false, false) => 0,
                 (true, true, false, false) => 1,
                 (false, false, true, true) => 2,
@@ -246,8 +273,8 @@ impl AliveCell {
         self.ty.is_fertile()
     }
 
-    pub const fn consumption(&self) -> f32 {
-        self.ty.consumption()
+    pub const fn consumption(&self, table: &ConsumptionTable) -> f32 {
+        self.ty.consumption(table)
     }
 
     pub const fn organics(&self) -> u8 {
@@ -255,7 +282,7 @@ impl AliveCell {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum LifeType {
     Pipe,
     Leaf,
@@ -263,50 +290,52 @@ pub enum LifeType {
     Reactor,
     Filter,
 
-    Stem(Genome),
+    Stem(GenomeId),
 }
 
 impl LifeType {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            LifeType::Pipe => "Pipe",
+            LifeType::Leaf => "Leaf",
+            LifeType::Root => "Root",
+            LifeType::Reactor => "Reactor",
+            LifeType::Filter => "Filter",
+            LifeType::Stem(_) => "Stem",
+        }
+    }
+
     pub const fn can_transfer(&self) -> bool {
         self.is_energy_generator() || self.is_pipe()
     }
 
     pub const fn is_energy_generator(&self) -> bool {
-        match self {
-            LifeType::Leaf | LifeType::Root | LifeType::Reactor | LifeType::Filter => true,
-            _ => false,
-        }
+        matches!(
+            self,
+            LifeType::Leaf | LifeType::Root | LifeType::Reactor | LifeType::Filter
+        )
     }
 
     pub const fn is_pipe(&self) -> bool {
-        match self {
-            LifeType::Pipe => true,
-            _ => false,
-        }
+        matches!(self, LifeType::Pipe)
     }
 
     pub const fn is_pipe_recipient(&self) -> bool {
-        match self {
-            LifeType::Pipe | LifeType::Stem(_) => true,
-            _ => false,
-        }
+        matches!(self, LifeType::Pipe | LifeType::Stem(_))
     }
 
     pub const fn is_fertile(&self) -> bool {
-        match self {
-            LifeType::Stem(_) => true,
-            _ => false,
-        }
+        matches!(self, LifeType::Stem(_))
     }
 
-    pub const fn consumption(&self) -> f32 {
+    pub const fn consumption(&self, table: &ConsumptionTable) -> f32 {
         match self {
-            LifeType::Pipe => 0.1,
-            LifeType::Leaf => 0.6,
-            LifeType::Stem(_) => 0.1,
-            LifeType::Root => 0.2,
-            LifeType::Reactor => 0.4,
-            LifeType::Filter => 0.3,
+            LifeType::Pipe => table.pipe,
+            LifeType::Leaf => table.leaf,
+            LifeType::Stem(_) => table.stem,
+            LifeType::Root => table.root,
+            LifeType::Reactor => table.reactor,
+            LifeType::Filter => table.filter,
         }
     }
 
@@ -321,7 +350,13 @@ impl LifeType {
         }
     }
 
-    pub fn make_newborn_cell(self, parent_dir: CellDir, steps_to_death: u16) -> LifeCell {
+    pub fn make_newborn_cell(
+        self,
+        parent_dir: CellDir,
+        steps_to_death: u16,
+        organism: OrganismId,
+        table: &ConsumptionTable,
+    ) -> LifeCell {
         let new_cell_energy_directions = if self.is_energy_generator() {
             EnergyDirections::from_direction(&parent_dir)
         } else {
@@ -335,7 +370,8 @@ impl LifeType {
 
         LifeCell::Alive(AliveCell::new(
             self,
-            2. * self.consumption(),
+            organism,
+            2. * self.consumption(table),
             new_cell_energy_directions,
             Some(parent_dir),
             steps_to_death,
@@ -343,7 +379,32 @@ impl LifeType {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+/// Energy consumed by each cell type per step
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsumptionTable {
+    pub pipe: f32,
+    pub leaf: f32,
+    pub stem: f32,
+    pub root: f32,
+    pub reactor: f32,
+    pub filter: f32,
+}
+
+impl Default for ConsumptionTable {
+    fn default() -> Self {
+        Self {
+            pipe: 0.1,
+            leaf: 0.6,
+            stem: 0.1,
+            root: 0.2,
+            reactor: 0.4,
+            filter: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub struct EnergyDirections {
     pub up: bool,
     pub down: bool,
@@ -1,14 +1,19 @@
 pub mod air_cell;
 pub mod life_cell;
 pub mod soil_cell;
+pub mod terrain_cell;
 
 use air_cell::AirCell;
 use life_cell::LifeCell;
+use serde::{Deserialize, Serialize};
 use soil_cell::SoilCell;
+use terrain_cell::TerrainCell;
 
-#[derive(Debug, Clone, Copy, Default, PartialEq)]
+/// Every layer of one cell, see [`crate::world::World::cell`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 pub struct WorldCell {
     pub life: LifeCell,
     pub soil: SoilCell,
     pub air: AirCell,
+    pub terrain: TerrainCell,
 }
@@ -1,7 +1,9 @@
+use serde::{Deserialize, Serialize};
+
 pub const MAX_ORGANIC_LIFE: u8 = 16;
 pub const MAX_ENERGY_LIFE: f32 = 32.;
 
-#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 pub struct SoilCell {
     pub organics: u8,
     pub energy: f32,
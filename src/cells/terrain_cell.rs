@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{grid::BoundaryCell, types::BoundaryMode};
+
+/// Tile of `life.png` drawn for walls
+pub const WALL_TEXTURE_ID: u32 = 38;
+
+/// Walls block births, organic movement and diffusion of soil energy and pollution
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TerrainCell {
+    #[default]
+    Open,
+    Wall,
+}
+
+impl TerrainCell {
+    pub const fn is_wall(&self) -> bool {
+        matches!(self, Self::Wall)
+    }
+}
+
+impl BoundaryCell for TerrainCell {
+    fn outside(boundary: BoundaryMode, _: &Self) -> Self {
+        match boundary {
+            BoundaryMode::Walls | BoundaryMode::Reflective => Self::Wall,
+            BoundaryMode::Wrap | BoundaryMode::Absorbing => Self::Open,
+        }
+    }
+}
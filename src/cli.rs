@@ -0,0 +1,128 @@
+use std::{fs, path::PathBuf, process};
+
+use clap::Parser;
+use serde::de::DeserializeOwned;
+use spectaculife::{
+    climate::ClimateSchedule,
+    types::{BoundaryMode, Settings, UpdateMode},
+};
+
+/// Spectacular life simulation
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// TOML file with simulation settings, overridden by the flags below
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// World width in cells
+    #[arg(long)]
+    width: Option<u32>,
+
+    /// World height in cells
+    #[arg(long)]
+    height: Option<u32>,
+
+    /// Seed of the simulation random number generator
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// What lies beyond the world edges: wrap, walls, absorbing or reflective
+    #[arg(long)]
+    boundary: Option<BoundaryMode>,
+
+    /// How cells are updated within a step: sequential, buffered or chunked
+    #[arg(long)]
+    update: Option<UpdateMode>,
+
+    /// Threads used by the chunked update, 0 for one per core
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// Soil organics above which life dies (except roots)
+    #[arg(long)]
+    max_organic_life: Option<u8>,
+
+    /// Soil energy above which life dies (except reactors)
+    #[arg(long)]
+    max_energy_life: Option<f32>,
+
+    /// Distance between stem cells placed on initialization
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    seed_spacing: Option<u32>,
+
+    /// TOML file with `[[event]]` tables changing the climate over time
+    #[arg(long)]
+    climate: Option<PathBuf>,
+
+    /// Run without a window for `--steps` steps
+    #[arg(long, requires = "steps")]
+    pub headless: bool,
+
+    /// Steps to run in headless mode
+    #[arg(long, requires = "headless")]
+    pub steps: Option<usize>,
+
+    /// CSV file to write population and resource statistics to
+    #[arg(long)]
+    pub stats: Option<PathBuf>,
+
+    /// Write statistics every N steps
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    pub stats_interval: u64,
+}
+
+impl Cli {
+    pub fn settings(&self) -> Settings {
+        let mut settings = match &self.config {
+            Some(path) => read_toml(path),
+            None => Settings::default(),
+        };
+
+        macro_rules! override_setting {
+            ($arg: ident, $field: ident) => {
+                if let Some(value) = self.$arg {
+                    settings.$field = value;
+                }
+            };
+        }
+
+        override_setting!(width, w);
+        override_setting!(height, h);
+        override_setting!(seed, seed);
+        override_setting!(boundary, boundary);
+        override_setting!(update, update);
+        override_setting!(threads, threads);
+        override_setting!(max_organic_life, max_organic_life);
+        override_setting!(max_energy_life, max_energy_life);
+        override_setting!(seed_spacing, seed_spacing);
+
+        if settings.w == 0 || settings.h == 0 {
+            exit_with_error("world size must be at least 1x1");
+        }
+
+        settings
+    }
+
+    pub fn climate(&self) -> ClimateSchedule {
+        match &self.climate {
+            Some(path) => read_toml(path),
+            None => ClimateSchedule::default(),
+        }
+    }
+}
+
+fn read_toml<T: DeserializeOwned>(path: &PathBuf) -> T {
+    let content = fs::read_to_string(path).unwrap_or_else(|err| {
+        exit_with_error(&format!("failed to read {}: {err}", path.display()))
+    });
+
+    toml::from_str(&content).unwrap_or_else(|err| {
+        exit_with_error(&format!("failed to parse {}: {err}", path.display()))
+    })
+}
+
+pub fn exit_with_error(message: &str) -> ! {
+    eprintln!("error: {message}");
+    process::exit(2)
+}
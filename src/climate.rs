@@ -0,0 +1,268 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cells::life_cell::genome::arena::GenomeArena,
+    types::{Settings, SimRng},
+    update::kill,
+    world::World,
+};
+
+/// Global parameter a climate event can change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClimateParameter {
+    LightIntensity,
+    SoilEnergyDiffusion,
+    SoilEnergyDecay,
+    PollutionDiffusion,
+    PollutionDecay,
+}
+
+impl ClimateParameter {
+    fn value_mut(self, settings: &mut Settings) -> &mut f32 {
+        match self {
+            Self::LightIntensity => &mut settings.light.intensity,
+            Self::SoilEnergyDiffusion => &mut settings.diffusion.soil_energy,
+            Self::SoilEnergyDecay => &mut settings.diffusion.soil_energy_decay,
+            Self::PollutionDiffusion => &mut settings.diffusion.pollution,
+            Self::PollutionDecay => &mut settings.diffusion.pollution_decay,
+        }
+    }
+}
+
+/// Rectangle of cells, the whole world when missing
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ClimateEvent {
+    /// Set `parameter` to `value`
+    Set {
+        parameter: ClimateParameter,
+        value: f32,
+    },
+    /// Move `parameter` linearly from `from` to `to` over `duration` steps
+    Ramp {
+        parameter: ClimateParameter,
+        from: f32,
+        to: f32,
+        duration: u32,
+    },
+    /// Remove all soil organics in the region
+    Drought { region: Option<Region> },
+    /// Add `amount` of pollution to every open cell in the region
+    PollutionSpike { amount: u8, region: Option<Region> },
+    /// Kill all life in a circle, centered on a random cell when no position is given
+    Meteor {
+        x: Option<u32>,
+        y: Option<u32>,
+        radius: u32,
+    },
+}
+
+/// Event happening at step `at` and then every `every` steps
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledEvent {
+    pub at: usize,
+    pub every: Option<usize>,
+
+    #[serde(flatten)]
+    pub event: ClimateEvent,
+}
+
+impl ScheduledEvent {
+    /// Steps since the last time the event happened, `None` before the first time
+    fn since_last(&self, step: usize) -> Option<usize> {
+        let since = step.checked_sub(self.at)?;
+
+        Some(match self.every {
+            Some(every) if every > 0 => since % every,
+            _ => since,
+        })
+    }
+}
+
+/// Perturbations of the environment over time, read from a TOML file of `[[event]]` tables
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClimateSchedule {
+    #[serde(default, rename = "event")]
+    pub events: Vec<ScheduledEvent>,
+}
+
+impl ClimateSchedule {
+    /// Apply every event due at `step`, in the order they are listed
+    pub fn apply(
+        &self,
+        step: usize,
+        settings: &mut Settings,
+        rng: &mut SimRng,
+        world: &mut World,
+        genomes: &mut GenomeArena,
+    ) {
+        for scheduled in &self.events {
+            let Some(since) = scheduled.since_last(step) else {
+                continue;
+            };
+
+            match scheduled.event {
+                ClimateEvent::Ramp {
+                    parameter,
+                    from,
+                    to,
+                    duration,
+                } => {
+                    if since <= duration as usize {
+                        // A ramp without duration jumps straight to `to`
+                        let progress = if since >= duration as usize {
+                            1.
+                        } else {
+                            since as f32 / duration as f32
+                        };
+                        *parameter.value_mut(settings) = from + (to - from) * progress;
+                    }
+                }
+
+                // Everything else happens once per occurrence
+                _ if since != 0 => {}
+
+                ClimateEvent::Set { parameter, value } => *parameter.value_mut(settings) = value,
+                ClimateEvent::Drought { region } => {
+                    for_region(settings, region, |x, y| world.organics.uset(x, y, 0));
+                }
+                ClimateEvent::PollutionSpike { amount, region } => {
+                    for_region(settings, region, |x, y| {
+                        if !world.terrain.uget(x, y).is_wall() {
+                            let pollution = world.pollution.uget_mut(x, y);
+                            *pollution = pollution.saturating_add(amount);
+                        }
+                    });
+                }
+                ClimateEvent::Meteor { x, y, radius } => {
+                    let x = x.unwrap_or_else(|| rng.gen_range(0..settings.w));
+                    let y = y.unwrap_or_else(|| rng.gen_range(0..settings.h));
+
+                    meteor(settings, world, genomes, x, y, radius);
+                }
+            }
+        }
+    }
+}
+
+fn for_region(settings: &Settings, region: Option<Region>, mut f: impl FnMut(u32, u32)) {
+    let region = region.unwrap_or(Region {
+        x: 0,
+        y: 0,
+        w: settings.w,
+        h: settings.h,
+    });
+
+    for y in region.y..(region.y.saturating_add(region.h)).min(settings.h) {
+        for x in region.x..(region.x.saturating_add(region.w)).min(settings.w) {
+            f(x, y);
+        }
+    }
+}
+
+/// Kill every alive cell within `radius` of `x`, `y`, the circle is clipped at the world edges
+fn meteor(
+    settings: &Settings,
+    world: &mut World,
+    genomes: &mut GenomeArena,
+    x: u32,
+    y: u32,
+    radius: u32,
+) {
+    let radius = radius as i64;
+
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            if dx * dx + dy * dy > radius * radius {
+                continue;
+            }
+
+            let (cx, cy) = (x as i64 + dx, y as i64 + dy);
+            if !(0..settings.w as i64).contains(&cx) || !(0..settings.h as i64).contains(&cy) {
+                continue;
+            }
+
+            let life = world.life.uget(cx as u32, cy as u32);
+            if let Some(id) = life.genome() {
+                genomes.release(id);
+            }
+
+            if life.is_alive() {
+                kill(&mut world.neighbourhood_mut(cx as u32, cy as u32));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use crate::types::BoundaryMode;
+
+    use super::*;
+
+    /// Pollution decay after applying `event` at every step up to `step`
+    fn pollution_decay(event: ClimateEvent, step: usize) -> f32 {
+        let schedule = ClimateSchedule {
+            events: vec![ScheduledEvent {
+                at: 5,
+                every: None,
+                event,
+            }],
+        };
+
+        let mut settings = Settings::default();
+        let mut rng = SimRng::seed_from_u64(7);
+        let mut world = World::new(4, 4, BoundaryMode::default());
+
+        for step in 0..=step {
+            schedule.apply(
+                step,
+                &mut settings,
+                &mut rng,
+                &mut world,
+                &mut GenomeArena::default(),
+            );
+        }
+
+        settings.diffusion.pollution_decay
+    }
+
+    #[test]
+    fn ramp_reaches_both_ends() {
+        let ramp = |duration| ClimateEvent::Ramp {
+            parameter: ClimateParameter::PollutionDecay,
+            from: 0.2,
+            to: 0.8,
+            duration,
+        };
+
+        let expect = |event, step, value: f32| {
+            let decay = pollution_decay(event, step);
+            assert!(
+                (decay - value).abs() < 1e-6,
+                "step {step}: {decay} != {value}"
+            );
+        };
+
+        expect(ramp(10), 4, Settings::default().diffusion.pollution_decay);
+        expect(ramp(10), 5, 0.2);
+        expect(ramp(10), 10, 0.5);
+        expect(ramp(10), 15, 0.8);
+        expect(ramp(10), 30, 0.8);
+
+        expect(ramp(0), 5, 0.8);
+        expect(ramp(0), 30, 0.8);
+    }
+}
@@ -0,0 +1,208 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    types::{BoundaryMode, Coord, Settings, SimRng},
+    world::World,
+};
+
+/// Largest rate for which diffusion over four neighbours stays stable
+pub const MAX_DIFFUSION_RATE: f32 = 0.25;
+
+/// How soil energy and pollution spread between cells
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiffusionSettings {
+    /// Share of the soil energy difference exchanged with each orthogonal neighbour per step,
+    /// up to [`MAX_DIFFUSION_RATE`]
+    pub soil_energy: f32,
+    /// Same for pollution, which moves in whole units only
+    pub pollution: f32,
+
+    /// Share of soil energy lost per step
+    pub soil_energy_decay: f32,
+    /// Share of pollution lost per step
+    pub pollution_decay: f32,
+}
+
+impl Default for DiffusionSettings {
+    fn default() -> Self {
+        Self {
+            soil_energy: 0.2,
+            pollution: MAX_DIFFUSION_RATE,
+
+            soil_energy_decay: 0.,
+            pollution_decay: 0.,
+        }
+    }
+}
+
+/// Spread soil energy and pollution over the whole world, then apply decay.
+///
+/// Every flow is computed from the world before this step and moved between a pair of cells,
+/// so apart from decay and absorbing edges nothing is created or lost. Walls and closed edges
+/// exchange nothing. Absorbing edges lose on purpose: the empty space beyond counts as a
+/// neighbour holding nothing, so edge cells drain into it.
+pub fn diffuse(settings: &Settings, rng: &mut SimRng, world: &mut World) {
+    let (w, h) = (settings.w, settings.h);
+    let diffusion = &settings.diffusion;
+
+    let energy_rate = diffusion.soil_energy.clamp(0., MAX_DIFFUSION_RATE);
+    let pollution_rate = diffusion.pollution.clamp(0., MAX_DIFFUSION_RATE);
+
+    let old_energy: Vec<f32> = world.soil_energy.iter().copied().collect();
+    let old_pollution: Vec<i32> = world.pollution.iter().map(|&p| p as i32).collect();
+    let wall: Vec<bool> = world.terrain.iter().map(|cell| cell.is_wall()).collect();
+
+    let mut energy = old_energy.clone();
+    let mut pollution = old_pollution.clone();
+
+    let terrain = &world.terrain;
+
+    for (i, (Coord { x, y }, _)) in terrain.enumerate_coords().enumerate() {
+        if wall[i] {
+            continue;
+        }
+
+        // Every pair is visited once, from its left or upper cell
+        for (dx, dy) in [(1, 0), (0, 1)] {
+            let Some((nx, ny)) = terrain.resolve(x as i64 + dx, y as i64 + dy) else {
+                continue;
+            };
+
+            // On a world one or two cells across, the pair across the seam is the cell itself or
+            // the pair already visited without wrapping
+            let (size, wrapped) = if dx == 1 { (w, nx <= x) } else { (h, ny <= y) };
+            if wrapped && size <= 2 {
+                continue;
+            }
+
+            let j = terrain.index(nx, ny);
+
+            if wall[j] {
+                continue;
+            }
+
+            let flow = energy_rate * (old_energy[i] - old_energy[j]);
+            energy[i] -= flow;
+            energy[j] += flow;
+
+            // Truncating keeps both cells within their old range, so no clamping is needed
+            let flow = (pollution_rate * (old_pollution[i] - old_pollution[j]) as f32) as i32;
+            pollution[i] -= flow;
+            pollution[j] += flow;
+        }
+
+        // Edges of an absorbing world lose what flows into the empty space beyond
+        if settings.boundary == BoundaryMode::Absorbing {
+            let edges =
+                (x == 0) as u32 + (x == w - 1) as u32 + (y == 0) as u32 + (y == h - 1) as u32;
+
+            energy[i] -= energy_rate * old_energy[i] * edges as f32;
+            pollution[i] -= (pollution_rate * old_pollution[i] as f32) as i32 * edges as i32;
+        }
+    }
+
+    let layers = world.soil_energy.iter_mut().zip(world.pollution.iter_mut());
+
+    for (i, (soil_energy, air_pollution)) in layers.enumerate() {
+        if wall[i] {
+            continue;
+        }
+
+        *soil_energy = (energy[i] * (1. - diffusion.soil_energy_decay)).max(0.);
+        *air_pollution =
+            decay_pollution(pollution[i], diffusion.pollution_decay, rng).clamp(0, 255) as u8;
+    }
+}
+
+/// Remove `decay` of `pollution`, the fractional part is removed with matching probability
+fn decay_pollution(pollution: i32, decay: f32, rng: &mut SimRng) -> i32 {
+    if decay <= 0. || pollution <= 0 {
+        return pollution;
+    }
+
+    let lost = pollution as f32 * decay.min(1.);
+    let whole = lost as i32;
+    let fraction = (lost - whole as f32) as f64;
+
+    pollution - whole - rng.gen_bool(fraction) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use crate::cells::terrain_cell::TerrainCell;
+
+    use super::*;
+
+    fn world(w: u32, h: u32, boundary: BoundaryMode) -> (Settings, World) {
+        let settings = Settings {
+            w,
+            h,
+            boundary,
+            ..Settings::default()
+        };
+
+        (settings, World::new(w, h, boundary))
+    }
+
+    fn totals(world: &World) -> (f32, u32) {
+        (
+            world.soil_energy.iter().sum(),
+            world.pollution.iter().map(|&p| p as u32).sum(),
+        )
+    }
+
+    #[test]
+    fn closed_worlds_keep_everything() {
+        let mut rng = SimRng::seed_from_u64(7);
+
+        for boundary in [
+            BoundaryMode::Wrap,
+            BoundaryMode::Walls,
+            BoundaryMode::Reflective,
+        ] {
+            for (w, h) in [(1, 1), (1, 5), (2, 5), (5, 2), (2, 2), (7, 6)] {
+                let (settings, mut world) = world(w, h, boundary);
+
+                for energy in world.soil_energy.iter_mut() {
+                    *energy = rng.gen_range(0. ..100.);
+                }
+                for pollution in world.pollution.iter_mut() {
+                    *pollution = rng.gen();
+                }
+                world.terrain.uset(0, h - 1, TerrainCell::Wall);
+
+                let (energy, pollution) = totals(&world);
+
+                for _ in 0..20 {
+                    diffuse(&settings, &mut rng, &mut world);
+                }
+
+                let (after_energy, after_pollution) = totals(&world);
+                let case = format!("{boundary:?} {w}x{h}");
+
+                assert!((after_energy - energy).abs() < energy * 1e-5, "{case}");
+                assert_eq!(after_pollution, pollution, "{case}");
+            }
+        }
+    }
+
+    #[test]
+    fn tiny_wrap_worlds_exchange_once() {
+        let mut rng = SimRng::seed_from_u64(7);
+
+        for (w, h) in [(2, 1), (1, 2)] {
+            let (settings, mut world) = world(w, h, BoundaryMode::Wrap);
+            world.soil_energy.uset(0, 0, 10.);
+
+            diffuse(&settings, &mut rng, &mut world);
+
+            let flow = 10. * settings.diffusion.soil_energy;
+            assert_eq!(world.soil_energy.uget(0, 0), &(10. - flow));
+            assert_eq!(world.soil_energy.uget(w - 1, h - 1), &flow);
+        }
+    }
+}
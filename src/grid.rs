@@ -1,69 +1,251 @@
-use bevy::prelude::Resource;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    types::{CellDir, Coord, Settings},
-    utils::get_continual_coord,
+    types::{BoundaryMode, CellDir, Coord, Settings},
+    utils::get_boundary_coord,
 };
 
-#[derive(Debug, Clone, Resource, Default)]
+/// Cells that can stand in for missing neighbours beyond a non-wrapping edge
+pub trait BoundaryCell: Copy {
+    /// Cell seen beyond the edge, `edge` is the nearest cell inside the grid
+    fn outside(boundary: BoundaryMode, edge: &Self) -> Self;
+}
+
+/// Amounts beyond the edge: reflective edges mirror the edge cell, the others hold nothing
+macro_rules! amount_boundary_cell {
+    ($($ty: ty),*) => {
+        $(
+            impl BoundaryCell for $ty {
+                fn outside(boundary: BoundaryMode, edge: &Self) -> Self {
+                    match boundary {
+                        BoundaryMode::Reflective => *edge,
+                        _ => Self::default(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+amount_boundary_cell!(u8, f32);
+
+/// Cells stored row by row
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Grid<T> {
-    grid: Vec<Vec<T>>,
+    cells: Vec<T>,
     width: u32,
     height: u32,
+    boundary: BoundaryMode,
+
+    /// Scratch neighbours handed out by [`Neighbourhood::new`], rebuilt for every neighbourhood
+    #[serde(skip)]
+    outside: [T; 9],
 }
 
 impl<T: std::default::Default + std::clone::Clone> Grid<T> {
-    pub fn new(width: u32, height: u32) -> Self {
-        let grid: Vec<Vec<T>> = vec![vec![T::default(); width as usize]; height as usize];
-
+    pub fn new(width: u32, height: u32, boundary: BoundaryMode) -> Self {
         Self {
-            grid,
+            cells: vec![T::default(); (width * height) as usize],
             width,
             height,
+            boundary,
+            outside: Default::default(),
         }
     }
 
-    pub fn get<'a>(&'a self, x: i64, y: i64) -> &'a T {
-        self.grid
-            .get(get_continual_coord(y, self.height) as usize)
-            .unwrap()
-            .get(get_continual_coord(x, self.width) as usize)
-            .unwrap()
+    pub const fn width(&self) -> u32 {
+        self.width
     }
 
-    pub fn uget<'a>(&'a self, x: u32, y: u32) -> &'a T {
-        self.grid.get(y as usize).unwrap().get(x as usize).unwrap()
+    pub const fn height(&self) -> u32 {
+        self.height
     }
 
-    pub fn get_mut<'a>(&'a mut self, x: i64, y: i64) -> &'a mut T {
-        self.grid
-            .get_mut(get_continual_coord(y, self.height) as usize)
-            .unwrap()
-            .get_mut(get_continual_coord(x, self.width) as usize)
-            .unwrap()
+    pub const fn boundary(&self) -> BoundaryMode {
+        self.boundary
     }
 
-    fn uget_mut<'a>(&'a mut self, x: u32, y: u32) -> &'a mut T {
-        self.grid
-            .get_mut(y as usize)
-            .unwrap()
-            .get_mut(x as usize)
-            .unwrap()
+    /// Amount of cells stored, `width * height` unless the grid was decoded from bad data
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Position of `x`, `y` in the row by row storage, as in [`Grid::iter`]
+    pub const fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// Cells row by row
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.cells.iter()
+    }
+
+    /// Cells row by row
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.cells.iter_mut()
+    }
+
+    /// Cells row by row together with their coordinates
+    pub fn enumerate_coords(&self) -> impl Iterator<Item = (Coord, &T)> {
+        let width = self.width as usize;
+
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(i, cell)| (Coord::new((i % width) as u32, (i / width) as u32), cell))
+    }
+
+    /// Cell at `x`, `y`, `None` beyond a non-wrapping edge
+    pub fn get(&self, x: i64, y: i64) -> Option<&T> {
+        let (x, y) = self.resolve(x, y)?;
+        Some(self.uget(x, y))
+    }
+
+    pub fn uget(&self, x: u32, y: u32) -> &T {
+        debug_assert!(x < self.width && y < self.height);
+        &self.cells[self.index(x, y)]
+    }
+
+    /// Cell at `x`, `y`, `None` beyond a non-wrapping edge
+    pub fn get_mut(&mut self, x: i64, y: i64) -> Option<&mut T> {
+        let (x, y) = self.resolve(x, y)?;
+        Some(self.uget_mut(x, y))
+    }
+
+    pub fn uget_mut(&mut self, x: u32, y: u32) -> &mut T {
+        debug_assert!(x < self.width && y < self.height);
+        let index = self.index(x, y);
+        &mut self.cells[index]
     }
 
     pub fn set(&mut self, x: i64, y: i64, item: T) {
-        let cell = self.get_mut(x, y);
-        *cell = item;
+        if let Some(cell) = self.get_mut(x, y) {
+            *cell = item;
+        }
     }
 
     pub fn uset(&mut self, x: u32, y: u32, item: T) {
         let cell = self.uget_mut(x, y);
         *cell = item;
     }
+
+    /// Copy of the neighbourhood of `x`, `y` in [`Neighbourhood::from_cells`] order, neighbours beyond a
+    /// non-wrapping edge are built by [`BoundaryCell::outside`]
+    pub fn neighbourhood(&self, x: u32, y: u32) -> [T; 9]
+    where
+        T: BoundaryCell,
+    {
+        std::array::from_fn(|slot| {
+            self.copy_or_outside(x as i64 + slot_dx(slot), y as i64 + slot_dy(slot))
+        })
+    }
+
+    /// Copy of the cell at `x`, `y`, or the cell seen there beyond a non-wrapping edge
+    fn copy_or_outside(&self, x: i64, y: i64) -> T
+    where
+        T: BoundaryCell,
+    {
+        match self.resolve(x, y) {
+            Some((x, y)) => *self.uget(x, y),
+            None => T::outside(
+                self.boundary,
+                self.uget(
+                    x.clamp(0, self.width as i64 - 1) as u32,
+                    y.clamp(0, self.height as i64 - 1) as u32,
+                ),
+            ),
+        }
+    }
+
+    /// Borrow the rectangles `columns` x `rows` at once, each with a one cell border, so cells
+    /// in different chunks can be updated in parallel. Chunks come row by row.
+    ///
+    /// Panics if the bordered rectangles overlap.
+    pub fn chunks_mut(&mut self, columns: &[Range<u32>], rows: &[Range<u32>]) -> Vec<Chunk<'_, T>>
+    where
+        T: BoundaryCell,
+    {
+        let boundary = self.boundary;
+
+        // Chunk and position inside it of every column or row
+        let axis = |ranges: &[Range<u32>], n: u32| {
+            let mut map: Vec<Option<(usize, usize)>> = vec![None; n as usize];
+
+            for (i, range) in ranges.iter().enumerate() {
+                for local in 0..range.len() + 2 {
+                    let Some(n) =
+                        get_boundary_coord(range.start as i64 - 1 + local as i64, n, boundary)
+                    else {
+                        continue;
+                    };
+
+                    assert!(map[n as usize].is_none(), "chunks overlap");
+                    map[n as usize] = Some((i, local));
+                }
+            }
+
+            map
+        };
+
+        let column_map = axis(columns, self.width);
+        let row_map = axis(rows, self.height);
+
+        let mut chunks: Vec<Chunk<'_, T>> = rows
+            .iter()
+            .flat_map(|rows| columns.iter().map(move |columns| (columns, rows)))
+            .map(|(columns, rows)| Chunk {
+                cells: (0..(columns.len() + 2) * (rows.len() + 2))
+                    .map(|_| None)
+                    .collect(),
+                columns: columns.clone(),
+                rows: rows.clone(),
+
+                width: self.width,
+                height: self.height,
+                boundary,
+
+                outside: Default::default(),
+            })
+            .collect();
+
+        for (y, row) in self.cells.chunks_mut(self.width as usize).enumerate() {
+            let Some((r, ly)) = row_map[y] else {
+                continue;
+            };
+
+            for (x, cell) in row.iter_mut().enumerate() {
+                let Some((c, lx)) = column_map[x] else {
+                    continue;
+                };
+
+                let chunk = &mut chunks[r * columns.len() + c];
+                let stride = chunk.columns.len() + 2;
+
+                chunk.cells[ly * stride + lx] = Some(cell);
+            }
+        }
+
+        chunks
+    }
+
+    /// Coordinates of `x`, `y` inside the grid, `None` beyond a non-wrapping edge
+    pub fn resolve(&self, x: i64, y: i64) -> Option<(u32, u32)> {
+        Some((
+            get_boundary_coord(x, self.width, self.boundary)?,
+            get_boundary_coord(y, self.height, self.boundary)?,
+        ))
+    }
 }
 
+/// A cell and its eight neighbours borrowed at once
 #[derive(Debug, PartialEq)]
-pub struct Area<'a, T> {
+pub struct Neighbourhood<'a, T> {
     pub up: &'a mut T,
     pub down: &'a mut T,
     pub left: &'a mut T,
@@ -81,54 +263,102 @@ pub struct Area<'a, T> {
     pub y: u32,
 }
 
-impl<'a, T: std::default::Default + std::clone::Clone + std::marker::Copy> Area<'a, T> {
-    pub fn new(grid: *mut Grid<T>, x: u32, y: u32) -> Self {
-        unsafe {
-            Self {
-                up: (&mut *grid).get_mut(x as i64, y as i64 - 1),
-                left: (&mut *grid).get_mut(x as i64 - 1, y as i64),
-                center: (&mut *grid).uget_mut(x, y),
-                right: (&mut *grid).get_mut(x as i64 + 1, y as i64),
-                down: (&mut *grid).get_mut(x as i64, y as i64 + 1),
+impl<'a, T: std::default::Default + std::clone::Clone + BoundaryCell> Neighbourhood<'a, T> {
+    /// Neighbourhood of `x`, `y`. Neighbours beyond a non-wrapping edge are scratch cells
+    /// built by [`BoundaryCell::outside`], and in worlds less than three cells wide or high so are
+    /// neighbours that are the same cell as the center or an earlier neighbour. Changes to scratch
+    /// cells are dropped.
+    pub fn new(grid: &'a mut Grid<T>, x: u32, y: u32) -> Self {
+        let indices: [Option<usize>; 9] = PRIORITY.map(|slot| {
+            let (nx, ny) = grid.resolve(x as i64 + slot_dx(slot), y as i64 + slot_dy(slot))?;
+            Some(grid.index(nx, ny))
+        });
 
-                up_left: (&mut *grid).get_mut(x as i64 - 1, y as i64 - 1),
-                up_right: (&mut *grid).get_mut(x as i64 + 1, y as i64 - 1),
+        for (i, &slot) in PRIORITY.iter().enumerate() {
+            if indices[i].is_none() || indices[..i].contains(&indices[i]) {
+                grid.outside[slot] =
+                    grid.copy_or_outside(x as i64 + slot_dx(slot), y as i64 + slot_dy(slot));
+            }
+        }
 
-                down_left: (&mut *grid).get_mut(x as i64 - 1, y as i64 + 1),
-                down_right: (&mut *grid).get_mut(x as i64 + 1, y as i64 + 1),
+        let Grid { cells, outside, .. } = grid;
 
-                x,
-                y,
-            }
+        let mut refs: [Option<&'a mut T>; 9] = Default::default();
+        for (cell, slot) in disjoint_mut(cells, indices).into_iter().zip(PRIORITY) {
+            refs[slot] = cell;
         }
+
+        Self::from_refs(refs, outside, x, y)
     }
 
-    pub fn get_up_coord(&self, settings: &Settings) -> Coord {
-        Coord {
-            x: self.x,
-            y: get_continual_coord(self.y as i64 - 1, settings.h),
+    /// Neighbourhood of nine cells detached from any grid, ordered row by row from the upper left
+    pub fn from_cells(cells: &'a mut [T; 9], x: u32, y: u32) -> Self {
+        Self::from_slots(cells.each_mut(), x, y)
+    }
+
+    /// Neighbourhood of `cells` in [`Neighbourhood::from_cells`] order, missing cells are taken from `outside`
+    fn from_refs(cells: [Option<&'a mut T>; 9], outside: &'a mut [T; 9], x: u32, y: u32) -> Self {
+        let mut outside = outside.each_mut().into_iter();
+
+        Self::from_slots(
+            cells.map(|cell| {
+                let scratch = outside.next().unwrap();
+                cell.unwrap_or(scratch)
+            }),
+            x,
+            y,
+        )
+    }
+
+    fn from_slots(cells: [&'a mut T; 9], x: u32, y: u32) -> Self {
+        let [up_left, up, up_right, left, center, right, down_left, down, down_right] = cells;
+
+        Self {
+            up,
+            down,
+            left,
+            right,
+
+            up_left,
+            up_right,
+
+            down_left,
+            down_right,
+
+            center,
+
+            x,
+            y,
         }
     }
 
-    pub fn get_down_coord(&self, settings: &Settings) -> Coord {
-        Coord {
+    /// Coordinates of the neighbours, `None` beyond a non-wrapping edge
+    pub fn get_up_coord(&self, settings: &Settings) -> Option<Coord> {
+        Some(Coord {
             x: self.x,
-            y: get_continual_coord(self.y as i64 + 1, settings.h),
-        }
+            y: get_boundary_coord(self.y as i64 - 1, settings.h, settings.boundary)?,
+        })
     }
 
-    pub fn get_left_coord(&self, settings: &Settings) -> Coord {
-        Coord {
-            x: get_continual_coord(self.x as i64 - 1, settings.w),
+    pub fn get_down_coord(&self, settings: &Settings) -> Option<Coord> {
+        Some(Coord {
+            x: self.x,
+            y: get_boundary_coord(self.y as i64 + 1, settings.h, settings.boundary)?,
+        })
+    }
+
+    pub fn get_left_coord(&self, settings: &Settings) -> Option<Coord> {
+        Some(Coord {
+            x: get_boundary_coord(self.x as i64 - 1, settings.w, settings.boundary)?,
             y: self.y,
-        }
+        })
     }
 
-    pub fn get_right_coord(&self, settings: &Settings) -> Coord {
-        Coord {
-            x: get_continual_coord(self.x as i64 + 1, settings.w),
+    pub fn get_right_coord(&self, settings: &Settings) -> Option<Coord> {
+        Some(Coord {
+            x: get_boundary_coord(self.x as i64 + 1, settings.w, settings.boundary)?,
             y: self.y,
-        }
+        })
     }
 
     pub fn get_center_coord(&self, _: &Settings) -> Coord {
@@ -138,7 +368,7 @@ impl<'a, T: std::default::Default + std::clone::Clone + std::marker::Copy> Area<
         }
     }
 
-    pub fn coord_from_dir(&self, dir: &CellDir, settings: &Settings) -> Coord {
+    pub fn coord_from_dir(&self, dir: &CellDir, settings: &Settings) -> Option<Coord> {
         match dir {
             CellDir::Up => self.get_up_coord(settings),
             CellDir::Down => self.get_down_coord(settings),
@@ -147,3 +377,216 @@ impl<'a, T: std::default::Default + std::clone::Clone + std::marker::Copy> Area<
         }
     }
 }
+
+/// Rectangle of a grid borrowed together with a one cell border, see [`Grid::chunks_mut`]
+pub struct Chunk<'a, T> {
+    /// Cells of the rectangle and its border row by row, `None` beyond a non-wrapping edge
+    cells: Vec<Option<&'a mut T>>,
+    pub columns: Range<u32>,
+    pub rows: Range<u32>,
+
+    width: u32,
+    height: u32,
+    boundary: BoundaryMode,
+
+    /// Scratch neighbours handed out by [`Chunk::neighbourhood`], rebuilt for every neighbourhood
+    outside: [T; 9],
+}
+
+impl<T: std::default::Default + std::clone::Clone + BoundaryCell> Chunk<'_, T> {
+    /// Cell at `x`, `y`, which must lie inside the rectangle
+    pub fn uget(&self, x: u32, y: u32) -> &T {
+        debug_assert!(self.columns.contains(&x) && self.rows.contains(&y));
+
+        let stride = self.columns.len() + 2;
+        let local =
+            (y - self.rows.start + 1) as usize * stride + (x - self.columns.start + 1) as usize;
+
+        self.cells[local]
+            .as_deref()
+            .expect("cells inside the rectangle belong to the chunk")
+    }
+
+    /// Neighbourhood of `x`, `y` like [`Neighbourhood::new`], the cell must lie inside the rectangle
+    pub fn neighbourhood(&mut self, x: u32, y: u32) -> Neighbourhood<'_, T> {
+        debug_assert!(self.columns.contains(&x) && self.rows.contains(&y));
+
+        let (left, top) = (self.columns.start as i64 - 1, self.rows.start as i64 - 1);
+        let stride = self.columns.len() + 2;
+        let local = |x: i64, y: i64| (y - top) as usize * stride + (x - left) as usize;
+
+        let slots: [(i64, i64); 9] =
+            std::array::from_fn(|slot| (x as i64 + slot_dx(slot), y as i64 + slot_dy(slot)));
+
+        for (slot, &(nx, ny)) in slots.iter().enumerate() {
+            if self.cells[local(nx, ny)].is_none() {
+                let edge = local(
+                    nx.clamp(0, self.width as i64 - 1),
+                    ny.clamp(0, self.height as i64 - 1),
+                );
+                let edge = self.cells[edge]
+                    .as_deref()
+                    .expect("edge cells belong to the chunk");
+
+                self.outside[slot] = T::outside(self.boundary, edge);
+            }
+        }
+
+        let Chunk { cells, outside, .. } = self;
+
+        let refs = disjoint_mut(cells, slots.map(|(nx, ny)| Some(local(nx, ny))));
+
+        Neighbourhood::from_refs(
+            refs.map(|cell| cell.and_then(|cell| cell.as_deref_mut())),
+            outside,
+            x,
+            y,
+        )
+    }
+}
+
+/// Neighbour slots by priority, the center first so that it is never the one replaced by a
+/// scratch copy when neighbours are the same cell
+const PRIORITY: [usize; 9] = [4, 1, 7, 3, 5, 0, 2, 6, 8];
+
+const fn slot_dx(slot: usize) -> i64 {
+    slot as i64 % 3 - 1
+}
+
+const fn slot_dy(slot: usize) -> i64 {
+    slot as i64 / 3 - 1
+}
+
+/// Mutable references to the elements at `indices`, `None` for missing indices and for indices
+/// already handed out to an earlier entry
+fn disjoint_mut<X, const N: usize>(
+    slice: &mut [X],
+    indices: [Option<usize>; N],
+) -> [Option<&mut X>; N] {
+    let mut order: [usize; N] = std::array::from_fn(|i| i);
+    order.sort_by_key(|&i| (indices[i], i));
+
+    let mut refs = std::array::from_fn(|_| None);
+    let mut rest = slice.iter_mut();
+    let mut next = 0;
+
+    for i in order {
+        if let Some(index) = indices[i] {
+            if index >= next {
+                refs[i] = rest.nth(index - next);
+                next = index + 1;
+            }
+        }
+    }
+
+    refs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDARIES: [BoundaryMode; 4] = [
+        BoundaryMode::Wrap,
+        BoundaryMode::Walls,
+        BoundaryMode::Absorbing,
+        BoundaryMode::Reflective,
+    ];
+
+    /// 4x3 grid holding the index of every cell plus one
+    fn numbered(boundary: BoundaryMode) -> Grid<u8> {
+        let mut grid = Grid::new(4, 3, boundary);
+        for (i, cell) in grid.iter_mut().enumerate() {
+            *cell = i as u8 + 1;
+        }
+        grid
+    }
+
+    #[test]
+    fn get_at_edges() {
+        for boundary in BOUNDARIES {
+            let grid = numbered(boundary);
+            let wrap = |cell: u8| (boundary == BoundaryMode::Wrap).then_some(cell);
+
+            // Inside every edge
+            assert_eq!(grid.get(0, 0), Some(&1), "{boundary:?}");
+            assert_eq!(grid.get(3, 2), Some(&12), "{boundary:?}");
+
+            // Beyond the left, right, upper and lower edges and two corners
+            assert_eq!(grid.get(-1, 1).copied(), wrap(8), "{boundary:?}");
+            assert_eq!(grid.get(4, 1).copied(), wrap(5), "{boundary:?}");
+            assert_eq!(grid.get(1, -1).copied(), wrap(10), "{boundary:?}");
+            assert_eq!(grid.get(1, 3).copied(), wrap(2), "{boundary:?}");
+            assert_eq!(grid.get(-1, -1).copied(), wrap(12), "{boundary:?}");
+            assert_eq!(grid.get(4, 3).copied(), wrap(1), "{boundary:?}");
+        }
+    }
+
+    #[test]
+    fn neighbourhood_beyond_edges() {
+        for boundary in BOUNDARIES {
+            let grid = numbered(boundary);
+
+            let expected = match boundary {
+                BoundaryMode::Wrap => [12, 9, 10, 4, 1, 2, 8, 5, 6],
+                BoundaryMode::Walls | BoundaryMode::Absorbing => [0, 0, 0, 0, 1, 2, 0, 5, 6],
+                // The edge cell nearest to each missing neighbour
+                BoundaryMode::Reflective => [1, 1, 2, 1, 1, 2, 5, 5, 6],
+            };
+
+            assert_eq!(grid.neighbourhood(0, 0), expected, "{boundary:?}");
+        }
+    }
+
+    #[test]
+    fn disjoint_mut_hands_out_each_index_once() {
+        let mut cells = [0u8; 4];
+
+        let refs = disjoint_mut(&mut cells, [Some(2), None, Some(0), Some(2), Some(3)]);
+        let handed_out = refs.each_ref().map(|cell| cell.is_some());
+        assert_eq!(handed_out, [true, false, true, false, true]);
+
+        for (value, cell) in refs.into_iter().flatten().enumerate() {
+            *cell = value as u8 + 1;
+        }
+        assert_eq!(cells, [2, 0, 1, 3]);
+    }
+
+    #[test]
+    fn wrapped_neighbourhoods_do_not_alias() {
+        for (w, h) in [(1, 1), (1, 5), (5, 1), (2, 5), (5, 2), (2, 2), (3, 3)] {
+            let mut grid: Grid<u8> = Grid::new(w, h, BoundaryMode::Wrap);
+
+            for y in 0..h {
+                for x in 0..w {
+                    let Neighbourhood {
+                        up,
+                        down,
+                        left,
+                        right,
+                        up_left,
+                        up_right,
+                        down_left,
+                        down_right,
+                        center,
+                        ..
+                    } = Neighbourhood::new(&mut grid, x, y);
+
+                    let mut views = [
+                        up_left, up, up_right, left, center, right, down_left, down, down_right,
+                    ];
+
+                    // A view sharing a cell with another one would see the later write
+                    for (slot, view) in views.iter_mut().enumerate() {
+                        **view = slot as u8;
+                    }
+                    for (slot, view) in views.iter().enumerate() {
+                        assert_eq!(**view, slot as u8, "{w}x{h} at {x}, {y}, slot {slot}");
+                    }
+
+                    assert_eq!(grid.uget(x, y), &4);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use spectaculife::{
+    climate::ClimateSchedule, simulation::Simulation, statistics::StepStatistics, types::Settings,
+};
+
+use crate::cli::exit_with_error;
+
+/// Run `steps` steps without a window, writing statistics every `interval` steps when `stats`
+/// is given
+pub fn run(
+    settings: Settings,
+    climate: ClimateSchedule,
+    steps: usize,
+    stats: Option<&Path>,
+    interval: usize,
+) {
+    let mut simulation = Simulation {
+        climate,
+        ..Simulation::new(settings)
+    };
+    simulation.initialize();
+
+    let mut csv = stats.map(|path| {
+        create_csv(path).unwrap_or_else(|err| {
+            exit_with_error(&format!("failed to create {}: {err}", path.display()))
+        })
+    });
+
+    for step in 0..=steps {
+        if step > 0 {
+            simulation.step();
+        }
+
+        if let Some(writer) = &mut csv {
+            if step % interval.max(1) == 0 {
+                StepStatistics::collect(&simulation)
+                    .write_csv_row(&mut *writer)
+                    .unwrap_or_else(|err| {
+                        exit_with_error(&format!("failed to write statistics: {err}"))
+                    });
+            }
+        }
+    }
+
+    if let Some(mut writer) = csv {
+        writer
+            .flush()
+            .unwrap_or_else(|err| exit_with_error(&format!("failed to write statistics: {err}")));
+    }
+
+    let stats = StepStatistics::collect(&simulation);
+    println!(
+        "step {}: {} alive cells, {:.1} life energy, {} pollution",
+        stats.step,
+        stats.alive(),
+        stats.life_energy,
+        stats.total_pollution
+    );
+}
+
+fn create_csv(path: &Path) -> io::Result<BufWriter<File>> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{}", StepStatistics::CSV_HEADER)?;
+    Ok(writer)
+}
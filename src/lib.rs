@@ -0,0 +1,17 @@
+pub mod brush;
+pub mod cells;
+pub mod climate;
+pub mod diffusion;
+pub mod grid;
+pub mod library;
+pub mod light;
+pub mod phylogeny;
+#[cfg(feature = "render")]
+pub mod plugins;
+pub mod simulation;
+pub mod snapshot;
+pub mod statistics;
+pub mod types;
+pub mod update;
+pub mod utils;
+pub mod world;
@@ -0,0 +1,86 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::cells::life_cell::genome::{text::GenomeParseError, Genome};
+
+/// Directory the library is persisted to
+pub const LIBRARY_DIR: &str = "genomes";
+
+const GENOME_EXTENSION: &str = "genome";
+
+/// Named genomes kept across world resets, optionally mirrored to `<dir>/<name>.genome`
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "render", derive(bevy::prelude::Resource))]
+pub struct GenomeLibrary {
+    slots: BTreeMap<String, Genome>,
+}
+
+impl GenomeLibrary {
+    /// Load every `.genome` file in `dir`, a missing directory gives an empty library
+    pub fn load_dir(dir: impl AsRef<Path>) -> io::Result<(Self, Vec<(PathBuf, String)>)> {
+        let mut library = Self::default();
+        let mut errors = Vec::new();
+
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok((library, errors)),
+            Err(err) => return Err(err),
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+
+            if path.extension().and_then(|ext| ext.to_str()) != Some(GENOME_EXTENSION) {
+                continue;
+            }
+
+            let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+                continue;
+            };
+
+            match fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|content| parse_genome(&content).map_err(|err| err.to_string()))
+            {
+                Ok(genome) => {
+                    library.slots.insert(name.to_owned(), genome);
+                }
+                Err(err) => errors.push((path, err)),
+            }
+        }
+
+        Ok((library, errors))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Genome> {
+        self.slots.get(name)
+    }
+
+    pub fn insert(&mut self, name: &str, genome: Genome) {
+        self.slots.insert(name.to_owned(), genome);
+    }
+
+    /// Write the genome in slot `name` to `<dir>/<name>.genome` in text form
+    pub fn save_slot(&self, dir: impl AsRef<Path>, name: &str) -> io::Result<PathBuf> {
+        let genome = self.get(name).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("no genome in slot {name}"))
+        })?;
+
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.as_ref().join(name).with_extension(GENOME_EXTENSION);
+        fs::write(&path, genome.to_string())?;
+
+        Ok(path)
+    }
+}
+
+/// Parse a genome in text form, falling back to base64
+pub fn parse_genome(content: &str) -> Result<Genome, GenomeParseError> {
+    content
+        .parse()
+        .or_else(|err| Genome::from_base64(content).map_err(|_| err))
+}
@@ -0,0 +1,82 @@
+use std::f32::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+/// Sunlight reaching the leaves, see [`LightSettings::light`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LightSettings {
+    /// Energy produced by an unshaded leaf in full light
+    pub intensity: f32,
+
+    /// Steps from one midnight to the next, `0` keeps it always noon
+    pub day_length: u32,
+    /// Light at midnight relative to noon
+    pub night_light: f32,
+
+    /// Steps from one midsummer to the next, `0` disables seasons
+    pub year_length: u32,
+    /// Light in midsummer is `1 + amplitude`, in midwinter `1 - amplitude`
+    pub season_amplitude: f32,
+
+    /// Light at the bottom row relative to the top row
+    pub bottom_light: f32,
+
+    /// Share of light taken by each alive neighbour (of eight)
+    pub shading: f32,
+}
+
+impl Default for LightSettings {
+    /// Constant light everywhere
+    fn default() -> Self {
+        Self {
+            intensity: 1.2,
+
+            day_length: 0,
+            night_light: 0.,
+
+            year_length: 0,
+            season_amplitude: 0.,
+
+            bottom_light: 1.,
+
+            shading: 0.,
+        }
+    }
+}
+
+impl LightSettings {
+    /// Light falling on row `y` of a world `h` rows high at `step`, before shading
+    pub fn light(&self, step: usize, y: u32, h: u32) -> f32 {
+        let day = match self.day_length {
+            0 => 1.,
+            length => {
+                let phase = (step % length as usize) as f32 / length as f32;
+                let sun = 0.5 - 0.5 * (phase * TAU).cos();
+
+                self.night_light + (1. - self.night_light) * sun
+            }
+        };
+
+        let season = match self.year_length {
+            0 => 1.,
+            length => {
+                let phase = (step % length as usize) as f32 / length as f32;
+
+                1. + self.season_amplitude * (phase * TAU).cos()
+            }
+        };
+
+        let latitude = match h {
+            0 | 1 => 1.,
+            h => 1. - (1. - self.bottom_light) * y as f32 / (h - 1) as f32,
+        };
+
+        (self.intensity * day * season * latitude).max(0.)
+    }
+
+    /// Part of the light left after `neighbours` alive cells around took their share
+    pub fn shade(&self, neighbours: u32) -> f32 {
+        (1. - self.shading * neighbours as f32).max(0.)
+    }
+}
@@ -1,17 +1,35 @@
-mod cells;
-mod grid;
-mod plugins;
-mod types;
-mod update;
-mod utils;
+mod cli;
+mod headless;
 
+#[cfg(feature = "render")]
 use bevy::{
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
     prelude::*,
 };
-use plugins::{control, world::WorldPlugin};
+use clap::Parser;
+use cli::Cli;
+#[cfg(feature = "render")]
+use spectaculife::plugins::{
+    control, graphs::GraphsPlugin, inspector::InspectorPlugin, statistics::StatisticsPlugin,
+    ui::UiPlugin, world::WorldPlugin,
+};
 
 fn main() {
+    let cli = Cli::parse();
+    let settings = cli.settings();
+    let climate = cli.climate();
+
+    if let (true, Some(steps)) = (cli.headless, cli.steps) {
+        return headless::run(
+            settings,
+            climate,
+            steps,
+            cli.stats.as_deref(),
+            cli.stats_interval as usize,
+        );
+    }
+
+    #[cfg(feature = "render")]
     App::new()
         .add_plugins((
             DefaultPlugins.set(WindowPlugin {
@@ -21,10 +39,20 @@ fn main() {
                 }),
                 ..default()
             }),
-            FrameTimeDiagnosticsPlugin::default(),
+            FrameTimeDiagnosticsPlugin,
             LogDiagnosticsPlugin::default(),
-            control::ControlPlugin::default(),
-            WorldPlugin::default(),
+            control::ControlPlugin,
+            WorldPlugin { settings, climate },
+            UiPlugin,
+            InspectorPlugin,
+            GraphsPlugin,
+            StatisticsPlugin {
+                path: cli.stats,
+                interval: cli.stats_interval as usize,
+            },
         ))
         .run();
+
+    #[cfg(not(feature = "render"))]
+    cli::exit_with_error("built without the `render` feature, run with --headless --steps N");
 }
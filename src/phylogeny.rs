@@ -0,0 +1,290 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cells::life_cell::genome::{arena::GenomeArena, LineageId};
+
+/// History of a single lineage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineageRecord {
+    pub lineage: LineageId,
+    pub parent: Option<LineageId>,
+
+    pub genome_hash: u64,
+    pub parent_hash: Option<u64>,
+
+    pub birth_step: usize,
+    pub extinction_step: Option<usize>,
+
+    pub population: usize,
+    pub peak_population: usize,
+}
+
+/// Database of every lineage that appeared in the world
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(
+    from = "BTreeMap<LineageId, LineageRecord>",
+    into = "BTreeMap<LineageId, LineageRecord>"
+)]
+pub struct Phylogeny {
+    records: BTreeMap<LineageId, LineageRecord>,
+    /// Lineages that are not extinct, the only records a census updates
+    living: BTreeSet<LineageId>,
+}
+
+impl Phylogeny {
+    pub fn records(&self) -> impl Iterator<Item = &LineageRecord> {
+        self.records.values()
+    }
+
+    pub fn get(&self, lineage: LineageId) -> Option<&LineageRecord> {
+        self.records.get(&lineage)
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+        self.living.clear();
+    }
+
+    /// Count stem cells of every lineage, registering new lineages and marking extinct ones.
+    /// Populations come from the reference counts of `genomes`, which must be exact.
+    pub fn census(&mut self, genomes: &GenomeArena, step: usize) {
+        let mut population: HashMap<LineageId, usize> = HashMap::new();
+
+        for (_, genome, refs) in genomes.iter() {
+            *population.entry(genome.lineage).or_default() += refs as usize;
+
+            if !self.records.contains_key(&genome.lineage) {
+                let parent_hash = genome
+                    .parent_lineage
+                    .and_then(|parent| self.records.get(&parent))
+                    .map(|parent| parent.genome_hash);
+
+                self.records.insert(
+                    genome.lineage,
+                    LineageRecord {
+                        lineage: genome.lineage,
+                        parent: genome.parent_lineage,
+                        genome_hash: genome.content_hash(),
+                        parent_hash,
+                        birth_step: step,
+                        extinction_step: None,
+                        population: 0,
+                        peak_population: 0,
+                    },
+                );
+                self.living.insert(genome.lineage);
+            }
+        }
+
+        self.living.retain(|lineage| {
+            let record = self
+                .records
+                .get_mut(lineage)
+                .expect("living lineages have a record");
+
+            record.population = population.get(lineage).copied().unwrap_or(0);
+            record.peak_population = record.peak_population.max(record.population);
+
+            if record.population == 0 {
+                record.extinction_step = Some(step);
+            }
+
+            record.extinction_step.is_none()
+        });
+    }
+
+    /// Export as JSON array of lineage records
+    pub fn to_json(&self) -> String {
+        let records: Vec<&LineageRecord> = self.records.values().collect();
+        serde_json::to_string_pretty(&records).expect("records are always serializable")
+    }
+
+    /// Export as Newick tree, lineages are named `L<id>` and branch lengths are in steps
+    pub fn to_newick(&self) -> String {
+        let mut children: BTreeMap<LineageId, Vec<LineageId>> = BTreeMap::new();
+        let mut roots = Vec::new();
+
+        for record in self.records.values() {
+            match record
+                .parent
+                .filter(|parent| self.records.contains_key(parent))
+            {
+                Some(parent) => children.entry(parent).or_default().push(record.lineage),
+                None => roots.push(record.lineage),
+            }
+        }
+
+        let branch_length = |lineage: LineageId| {
+            let record = &self.records[&lineage];
+            let parent_birth = record
+                .parent
+                .and_then(|parent| self.records.get(&parent))
+                .map_or(record.birth_step, |parent| parent.birth_step);
+
+            record.birth_step.saturating_sub(parent_birth)
+        };
+
+        // Iterative traversal, lineages can be nested deeper than the stack allows
+        enum Token {
+            Enter(LineageId),
+            Exit(LineageId),
+            Comma,
+        }
+
+        fn push_list(stack: &mut Vec<Token>, lineages: &[LineageId]) {
+            for (i, lineage) in lineages.iter().enumerate().rev() {
+                stack.push(Token::Enter(*lineage));
+
+                if i != 0 {
+                    stack.push(Token::Comma);
+                }
+            }
+        }
+
+        let mut newick = String::from("(");
+        let mut stack = Vec::new();
+        push_list(&mut stack, &roots);
+
+        while let Some(token) = stack.pop() {
+            match token {
+                Token::Enter(lineage) => match children.get(&lineage) {
+                    Some(kids) => {
+                        newick.push('(');
+                        stack.push(Token::Exit(lineage));
+                        push_list(&mut stack, kids);
+                    }
+                    None => newick.push_str(&format!("L{}:{}", lineage.0, branch_length(lineage))),
+                },
+                Token::Exit(lineage) => {
+                    newick.push_str(&format!(")L{}:{}", lineage.0, branch_length(lineage)))
+                }
+                Token::Comma => newick.push(','),
+            }
+        }
+
+        newick.push_str(");");
+        newick
+    }
+}
+
+impl From<BTreeMap<LineageId, LineageRecord>> for Phylogeny {
+    fn from(records: BTreeMap<LineageId, LineageRecord>) -> Self {
+        let living = records
+            .values()
+            .filter(|record| record.extinction_step.is_none())
+            .map(|record| record.lineage)
+            .collect();
+
+        Self { records, living }
+    }
+}
+
+impl From<Phylogeny> for BTreeMap<LineageId, LineageRecord> {
+    fn from(phylogeny: Phylogeny) -> Self {
+        phylogeny.records
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+
+    use crate::{
+        cells::life_cell::genome::{arena::GenomeId, Genome},
+        types::SimRng,
+    };
+
+    use super::*;
+
+    /// Genome of `lineage` used by `stems` stem cells
+    fn stems(
+        genomes: &mut GenomeArena,
+        rng: &mut SimRng,
+        lineage: u64,
+        parent: Option<u64>,
+        stems: usize,
+    ) -> GenomeId {
+        let id = genomes.insert(Genome {
+            lineage: LineageId(lineage),
+            parent_lineage: parent.map(LineageId),
+            ..rng.gen()
+        });
+
+        for _ in 0..stems {
+            genomes.acquire(id);
+        }
+
+        id
+    }
+
+    fn population(phylogeny: &Phylogeny, lineage: u64) -> (usize, usize, Option<usize>) {
+        let record = phylogeny.get(LineageId(lineage)).unwrap();
+        (
+            record.population,
+            record.peak_population,
+            record.extinction_step,
+        )
+    }
+
+    /// Lineage 2 dies out at step 5 while lineage 1 branches into 3 and 4, then 3 dies out at 8
+    fn fixture() -> (Phylogeny, GenomeArena, [GenomeId; 4]) {
+        let mut rng = SimRng::seed_from_u64(7);
+        let mut genomes = GenomeArena::default();
+        let mut phylogeny = Phylogeny::default();
+
+        let first = stems(&mut genomes, &mut rng, 1, None, 2);
+        let second = stems(&mut genomes, &mut rng, 2, None, 1);
+        phylogeny.census(&genomes, 0);
+
+        let third = stems(&mut genomes, &mut rng, 3, Some(1), 1);
+        let fourth = stems(&mut genomes, &mut rng, 4, Some(1), 1);
+        genomes.release(first);
+        genomes.release(second);
+        phylogeny.census(&genomes, 5);
+
+        genomes.release(third);
+        phylogeny.census(&genomes, 8);
+
+        (phylogeny, genomes, [first, second, third, fourth])
+    }
+
+    #[test]
+    fn census_counts_living_lineages() {
+        let (phylogeny, _, _) = fixture();
+
+        assert_eq!(population(&phylogeny, 1), (1, 2, None));
+        assert_eq!(population(&phylogeny, 2), (0, 1, Some(5)));
+        assert_eq!(population(&phylogeny, 3), (0, 1, Some(8)));
+        assert_eq!(population(&phylogeny, 4), (1, 1, None));
+
+        assert_eq!(phylogeny.get(LineageId(3)).unwrap().birth_step, 5);
+        assert_eq!(
+            phylogeny.get(LineageId(3)).unwrap().parent_hash,
+            Some(phylogeny.get(LineageId(1)).unwrap().genome_hash)
+        );
+    }
+
+    #[test]
+    fn loading_keeps_extinct_lineages_extinct() {
+        let (phylogeny, mut genomes, [_, _, _, fourth]) = fixture();
+
+        let bytes = bincode::serialize(&phylogeny).unwrap();
+        let mut loaded: Phylogeny = bincode::deserialize(&bytes).unwrap();
+
+        genomes.release(fourth);
+        loaded.census(&genomes, 9);
+
+        assert_eq!(population(&loaded, 1), (1, 2, None));
+        assert_eq!(population(&loaded, 2), (0, 1, Some(5)));
+        assert_eq!(population(&loaded, 3), (0, 1, Some(8)));
+        assert_eq!(population(&loaded, 4), (0, 1, Some(9)));
+    }
+
+    #[test]
+    fn newick_export() {
+        let (phylogeny, _, _) = fixture();
+
+        assert_eq!(phylogeny.to_newick(), "((L3:5,L4:5)L1:0,L2:0);");
+    }
+}
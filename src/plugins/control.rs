@@ -1,6 +1,8 @@
+use std::fs;
+
 use bevy::{
     input::{
-        common_conditions::{input_just_pressed, input_pressed},
+        common_conditions::input_just_pressed,
         mouse::{MouseMotion, MouseWheel},
     },
     math::{uvec2, vec3},
@@ -8,7 +10,39 @@ use bevy::{
 };
 use bevy_fast_tilemap::Map;
 
-use crate::types::State;
+use crate::{
+    brush::{Brush, BrushLayer},
+    cells::life_cell::genome::Genome,
+    library::{parse_genome, GenomeLibrary, LIBRARY_DIR},
+    simulation::Simulation,
+    snapshot::QUICKSAVE_PATH,
+    types::State,
+};
+
+const PHYLOGENY_NEWICK_PATH: &str = "phylogeny.nwk";
+const PHYLOGENY_JSON_PATH: &str = "phylogeny.json";
+
+/// Genome loaded into the stamp tool with F8, in text or base64 form
+const STAMP_GENOME_PATH: &str = "stamp.genome";
+
+const STAMP_ENERGY_STEP: f32 = 10.;
+
+const MAX_BRUSH_RADIUS: u32 = 64;
+const MIN_BRUSH_STRENGTH: f32 = 0.25;
+const MAX_BRUSH_STRENGTH: f32 = 256.;
+
+/// Keys of the library slots `slot1` to `slot9`
+const SLOT_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
 
 use super::world::next_step;
 
@@ -17,69 +51,355 @@ pub struct ControlPlugin;
 
 impl Plugin for ControlPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (
-                keyboard_input,
-                mouse_controls_camera,
-                update_cursor_position,
-                next_step.run_if(input_just_pressed(KeyCode::KeyN)),
-            ),
-        );
+        app.insert_resource(StampTool::default())
+            .insert_resource(BrushTool::default())
+            .insert_resource(GenomeLibrary::default())
+            .add_systems(Startup, load_library)
+            .add_systems(
+                Update,
+                (
+                    keyboard_input,
+                    stamp_controls,
+                    brush_controls,
+                    library_controls,
+                    load_stamp_genome.run_if(input_just_pressed(KeyCode::F8)),
+                    quick_save.run_if(input_just_pressed(KeyCode::F5)),
+                    quick_load.run_if(input_just_pressed(KeyCode::F9)),
+                    export_phylogeny.run_if(input_just_pressed(KeyCode::F6)),
+                    mouse_controls_camera,
+                    update_cursor_position,
+                    next_step.run_if(input_just_pressed(KeyCode::KeyN)),
+                ),
+            );
+    }
+}
+
+/// Places copies of a genome as new organisms, toggled with `T`
+#[derive(Resource, Debug)]
+pub struct StampTool {
+    pub active: bool,
+    pub genome: Option<Genome>,
+    pub energy: f32,
+}
+
+impl Default for StampTool {
+    fn default() -> Self {
+        Self {
+            active: false,
+            genome: None,
+            energy: 100.,
+        }
+    }
+}
+
+/// Paints world layers with the mouse, `B` cycles through the layers and off
+#[derive(Resource, Debug)]
+pub struct BrushTool {
+    pub layer: Option<BrushLayer>,
+    pub radius: u32,
+    pub strength: f32,
+}
+
+impl Default for BrushTool {
+    fn default() -> Self {
+        Self {
+            layer: None,
+            radius: 3,
+            strength: 4.,
+        }
     }
 }
 
 fn keyboard_input(
     keys: Res<ButtonInput<KeyCode>>,
-    mut state: ResMut<State>,
+    mut simulation: ResMut<Simulation>,
     mut maps: Query<(&Handle<Map>, &mut Visibility)>,
 ) {
     if keys.just_pressed(KeyCode::Space) {
-        state.paused = !state.paused;
+        simulation.state.paused = !simulation.state.paused;
     }
 
     if keys.just_pressed(KeyCode::KeyI) {
-        state.initialized = false;
+        simulation.state.initialized = false;
+    }
+
+    if keys.just_pressed(KeyCode::Tab) {
+        simulation.state.inspector_visible = !simulation.state.inspector_visible;
+    }
+
+    if keys.just_pressed(KeyCode::KeyG) {
+        simulation.state.graphs_visible = !simulation.state.graphs_visible;
     }
 
     if keys.just_pressed(KeyCode::KeyO) {
-        let (_, mut visibility) = maps.iter_mut().nth(0).unwrap();
-        if state.organic_visible {
+        let (_, mut visibility) = maps.iter_mut().next().unwrap();
+        if simulation.state.organic_visible {
             *visibility = Visibility::Hidden;
-            state.organic_visible = false;
+            simulation.state.organic_visible = false;
         } else {
             *visibility = Visibility::Visible;
-            state.organic_visible = true;
+            simulation.state.organic_visible = true;
         }
     }
 
     if keys.just_pressed(KeyCode::KeyL) {
         let (_, mut visibility) = maps.iter_mut().nth(1).unwrap();
-        if state.life_visible {
+        if simulation.state.life_visible {
             *visibility = Visibility::Hidden;
-            state.life_visible = false;
+            simulation.state.life_visible = false;
         } else {
             *visibility = Visibility::Visible;
-            state.life_visible = true;
+            simulation.state.life_visible = true;
         }
     }
 
     if keys.just_pressed(KeyCode::KeyP) {
         let (_, mut visibility) = maps.iter_mut().nth(2).unwrap();
-        if state.pollution_visible {
+        if simulation.state.pollution_visible {
             *visibility = Visibility::Hidden;
-            state.pollution_visible = false;
+            simulation.state.pollution_visible = false;
         } else {
             *visibility = Visibility::Visible;
-            state.pollution_visible = true;
+            simulation.state.pollution_visible = true;
+        }
+    }
+}
+
+/// `T` toggles the stamp, `C` picks the genome of the organism under the cursor,
+/// `[` and `]` change the starting energy, LMB places the organism
+fn stamp_controls(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    mut stamp: ResMut<StampTool>,
+    mut brush: ResMut<BrushTool>,
+    mut simulation: ResMut<Simulation>,
+) {
+    if keys.just_pressed(KeyCode::KeyT) {
+        stamp.active = !stamp.active;
+
+        if stamp.active {
+            brush.layer = None;
+        }
+    }
+
+    if keys.just_pressed(KeyCode::KeyC) {
+        match simulation.organism_genome(simulation.state.cursor_position) {
+            Some(genome) => {
+                stamp.genome = Some(genome);
+                info!("Copied genome {:016x} to stamp", genome.content_hash());
+            }
+            None => warn!("No organism with a known genome under cursor"),
+        }
+    }
+
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        stamp.energy = (stamp.energy - STAMP_ENERGY_STEP).max(STAMP_ENERGY_STEP);
+    }
+
+    if keys.just_pressed(KeyCode::BracketRight) {
+        stamp.energy += STAMP_ENERGY_STEP;
+    }
+
+    if stamp.active && mouse_button.just_pressed(MouseButton::Left) {
+        let Some(genome) = stamp.genome else {
+            return warn!("Stamp has no genome, copy one with C or load one with F8");
+        };
+
+        let cursor = simulation.state.cursor_position;
+        if !simulation.place_stem(cursor, genome, stamp.energy) {
+            warn!("Cannot place an organism on a wall");
+        }
+    }
+}
+
+/// `B` picks the layer, `-` and `=` change the radius, `,` and `.` change the strength,
+/// LMB paints and `Shift+LMB` erases
+fn brush_controls(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    mut brush: ResMut<BrushTool>,
+    mut stamp: ResMut<StampTool>,
+    mut simulation: ResMut<Simulation>,
+) {
+    if keys.just_pressed(KeyCode::KeyB) {
+        brush.layer = match brush.layer {
+            None => Some(BrushLayer::Organics),
+            Some(layer) => layer.next(),
+        };
+
+        if brush.layer.is_some() {
+            stamp.active = false;
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Minus) {
+        brush.radius = brush.radius.saturating_sub(1);
+    }
+
+    if keys.just_pressed(KeyCode::Equal) {
+        brush.radius = (brush.radius + 1).min(MAX_BRUSH_RADIUS);
+    }
+
+    if keys.just_pressed(KeyCode::Comma) {
+        brush.strength = (brush.strength / 2.).max(MIN_BRUSH_STRENGTH);
+    }
+
+    if keys.just_pressed(KeyCode::Period) {
+        brush.strength = (brush.strength * 2.).min(MAX_BRUSH_STRENGTH);
+    }
+
+    let Some(layer) = brush.layer else {
+        return;
+    };
+
+    if mouse_button.pressed(MouseButton::Left) {
+        let erase = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        let cursor = simulation.state.cursor_position;
+        let simulation = &mut *simulation;
+
+        Brush {
+            layer,
+            radius: brush.radius,
+            strength: brush.strength,
+        }
+        .apply(
+            &mut simulation.world,
+            &mut simulation.genomes,
+            cursor,
+            erase,
+        );
+    }
+}
+
+fn load_stamp_genome(mut stamp: ResMut<StampTool>) {
+    let content = match fs::read_to_string(STAMP_GENOME_PATH) {
+        Ok(content) => content,
+        Err(err) => return error!("Failed to read {STAMP_GENOME_PATH}: {err}"),
+    };
+
+    let genome = match parse_genome(&content) {
+        Ok(genome) => genome,
+        Err(err) => return error!("Failed to parse {STAMP_GENOME_PATH}: {err}"),
+    };
+
+    stamp.genome = Some(genome);
+    info!("Loaded stamp genome from {STAMP_GENOME_PATH}");
+}
+
+fn load_library(mut library: ResMut<GenomeLibrary>) {
+    match GenomeLibrary::load_dir(LIBRARY_DIR) {
+        Ok((loaded, errors)) => {
+            for (path, err) in errors {
+                error!("Failed to load genome {}: {err}", path.display());
+            }
+
+            *library = loaded;
+        }
+        Err(err) => error!("Failed to read {LIBRARY_DIR}: {err}"),
+    }
+}
+
+/// `Ctrl+1..9` copies the genome of the organism under the cursor to a library slot,
+/// with `Shift` the slot is also written to disk, `1..9` puts the slot into the stamp
+fn library_controls(
+    keys: Res<ButtonInput<KeyCode>>,
+    simulation: Res<Simulation>,
+    mut library: ResMut<GenomeLibrary>,
+    mut stamp: ResMut<StampTool>,
+) {
+    let Some(slot) = SLOT_KEYS.iter().position(|key| keys.just_pressed(*key)) else {
+        return;
+    };
+
+    let name = format!("slot{}", slot + 1);
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if !ctrl {
+        match library.get(&name) {
+            Some(genome) => {
+                stamp.genome = Some(*genome);
+                info!("Loaded {name} into stamp");
+            }
+            None => warn!("Library slot {name} is empty"),
+        }
+
+        return;
+    }
+
+    let Some(genome) = simulation.organism_genome(simulation.state.cursor_position) else {
+        return warn!("No organism with a known genome under cursor");
+    };
+
+    library.insert(&name, genome);
+    info!("Copied genome {:016x} to {name}", genome.content_hash());
+
+    if shift {
+        match library.save_slot(LIBRARY_DIR, &name) {
+            Ok(path) => info!("Saved {name} to {}", path.display()),
+            Err(err) => error!("Failed to save {name}: {err}"),
         }
     }
 }
 
-/// Use RMB for panning
+fn quick_save(simulation: Res<Simulation>) {
+    match simulation.save(QUICKSAVE_PATH) {
+        Ok(()) => info!("Saved world to {QUICKSAVE_PATH}"),
+        Err(err) => error!("Failed to save world: {err}"),
+    }
+}
+
+fn quick_load(mut simulation: ResMut<Simulation>) {
+    let loaded = match Simulation::load(QUICKSAVE_PATH) {
+        Ok(loaded) => loaded,
+        Err(err) => return error!("Failed to load world: {err}"),
+    };
+
+    // Tilemaps are created once with the startup world size
+    if (loaded.settings.w, loaded.settings.h) != (simulation.settings.w, simulation.settings.h) {
+        return error!(
+            "Snapshot world is {}x{}, current world is {}x{}",
+            loaded.settings.w, loaded.settings.h, simulation.settings.w, simulation.settings.h
+        );
+    }
+
+    let state = simulation.state.clone();
+    let climate = simulation.climate.clone();
+
+    *simulation = Simulation {
+        state: State {
+            simulation_step: loaded.state.simulation_step,
+            ids: loaded.state.ids,
+            initialized: true,
+            ..state
+        },
+        climate,
+        ..loaded
+    };
+
+    info!("Loaded world from {QUICKSAVE_PATH}");
+}
+
+fn export_phylogeny(simulation: Res<Simulation>) {
+    let exports = [
+        (PHYLOGENY_NEWICK_PATH, simulation.phylogeny.to_newick()),
+        (PHYLOGENY_JSON_PATH, simulation.phylogeny.to_json()),
+    ];
+
+    for (path, content) in exports {
+        match fs::write(path, content) {
+            Ok(()) => info!("Exported phylogeny to {path}"),
+            Err(err) => error!("Failed to export phylogeny to {path}: {err}"),
+        }
+    }
+}
+
+/// Use RMB (or LMB when no tool is active) for panning
 /// Use scroll wheel for zooming
 fn mouse_controls_camera(
     mouse_button: Res<ButtonInput<MouseButton>>,
+    stamp: Res<StampTool>,
+    brush: Res<BrushTool>,
     mut mouse_motion_events: EventReader<MouseMotion>,
     mut mouse_wheel_events: EventReader<MouseWheel>,
     mut camera_query: Query<(
@@ -90,7 +410,11 @@ fn mouse_controls_camera(
     )>,
 ) {
     for event in mouse_motion_events.read() {
-        if mouse_button.pressed(MouseButton::Left) || mouse_button.pressed(MouseButton::Right) {
+        let tool_active = stamp.active || brush.layer.is_some();
+
+        if (!tool_active && mouse_button.pressed(MouseButton::Left))
+            || mouse_button.pressed(MouseButton::Right)
+        {
             for (_, mut transform, _, _) in camera_query.iter_mut() {
                 transform.translation.x -= event.delta.x * transform.scale.x;
                 transform.translation.y += event.delta.y * transform.scale.y;
@@ -118,7 +442,7 @@ fn mouse_controls_camera(
 fn update_cursor_position(
     mut cursor_moved_events: EventReader<CursorMoved>,
     mut camera_query: Query<(&GlobalTransform, &Camera), With<OrthographicProjection>>,
-    mut state: ResMut<State>,
+    mut simulation: ResMut<Simulation>,
     maps: Query<&Handle<Map>>,
 
     materials: ResMut<Assets<Map>>,
@@ -137,6 +461,8 @@ fn update_cursor_position(
                     .as_uvec2()
                     .clamp(uvec2(0, 0), map.map_size() - uvec2(1, 1));
 
+                // Cursor movement alone should not trigger a map redraw
+                let state = &mut simulation.bypass_change_detection().state;
                 state.cursor_position.x = coord.x;
                 state.cursor_position.y = coord.y;
             }
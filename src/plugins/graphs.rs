@@ -0,0 +1,301 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{simulation::Simulation, statistics::StepStatistics};
+
+use super::world::next_step;
+
+/// Rolling line charts of population and resources, toggled with `G`.
+/// Statistics are only collected while the charts are shown, the lines break where they are
+/// missing.
+#[derive(Default)]
+pub struct GraphsPlugin;
+
+impl Plugin for GraphsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GraphHistory::default())
+            .add_systems(Startup, startup)
+            .add_systems(
+                Update,
+                (
+                    record_history
+                        .after(next_step)
+                        .run_if(resource_changed::<Simulation>)
+                        .run_if(graphs_visible),
+                    mark_gap.run_if(not(graphs_visible)),
+                    (draw_graphs, update_legend).run_if(graphs_visible),
+                    update_legend_visibility.run_if(resource_changed::<Simulation>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Steps shown in the charts
+const HISTORY_LEN: usize = 512;
+
+const MARGIN: f32 = 8.;
+const CHART_SIZE: Vec2 = Vec2::new(400., 120.);
+
+#[derive(Resource, Default)]
+struct GraphHistory {
+    samples: VecDeque<Sample>,
+    /// Steps went by without samples since the last one
+    gap: bool,
+}
+
+struct Sample {
+    statistics: StepStatistics,
+    /// Not connected to the previous sample
+    after_gap: bool,
+}
+
+struct Series {
+    name: &'static str,
+    color: Color,
+    value: fn(&StepStatistics) -> f64,
+}
+
+const POPULATION: [Series; 6] = [
+    Series {
+        name: "Pipe",
+        color: Color::GRAY,
+        value: |s| s.pipe as f64,
+    },
+    Series {
+        name: "Leaf",
+        color: Color::GREEN,
+        value: |s| s.leaf as f64,
+    },
+    Series {
+        name: "Root",
+        color: Color::ORANGE,
+        value: |s| s.root as f64,
+    },
+    Series {
+        name: "Reactor",
+        color: Color::RED,
+        value: |s| s.reactor as f64,
+    },
+    Series {
+        name: "Filter",
+        color: Color::CYAN,
+        value: |s| s.filter as f64,
+    },
+    Series {
+        name: "Stem",
+        color: Color::YELLOW,
+        value: |s| s.stem as f64,
+    },
+];
+
+const RESOURCES: [Series; 3] = [
+    Series {
+        name: "Organics",
+        color: Color::rgb(0.6, 0.4, 0.2),
+        value: |s| s.soil_organics as f64,
+    },
+    Series {
+        name: "Soil energy",
+        color: Color::PURPLE,
+        value: |s| s.soil_energy,
+    },
+    Series {
+        name: "Pollution",
+        color: Color::WHITE,
+        value: |s| s.total_pollution as f64,
+    },
+];
+
+#[derive(Component)]
+struct Legend;
+
+fn startup(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            visibility: Visibility::Hidden,
+            ..default()
+        }
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(MARGIN),
+            left: Val::Px(CHART_SIZE.x + MARGIN * 2.),
+            ..default()
+        }),
+        Legend,
+    ));
+}
+
+fn graphs_visible(simulation: Res<Simulation>) -> bool {
+    simulation.state.graphs_visible
+}
+
+fn mark_gap(simulation: Res<Simulation>, mut history: ResMut<GraphHistory>) {
+    let step = simulation.state.simulation_step;
+
+    if !history.gap
+        && history
+            .samples
+            .back()
+            .is_some_and(|last| last.statistics.step != step)
+    {
+        history.gap = true;
+    }
+}
+
+fn record_history(simulation: Res<Simulation>, mut history: ResMut<GraphHistory>) {
+    let step = simulation.state.simulation_step;
+
+    if history
+        .samples
+        .back()
+        .is_some_and(|last| last.statistics.step == step)
+    {
+        return;
+    }
+
+    // World was reinitialized or loaded from a snapshot
+    if history
+        .samples
+        .back()
+        .is_some_and(|last| last.statistics.step > step)
+    {
+        history.samples.clear();
+    }
+
+    while history
+        .samples
+        .front()
+        .is_some_and(|first| first.statistics.step + HISTORY_LEN <= step)
+    {
+        history.samples.pop_front();
+    }
+
+    let after_gap = std::mem::take(&mut history.gap);
+    history.samples.push_back(Sample {
+        statistics: StepStatistics::collect(&simulation),
+        after_gap,
+    });
+}
+
+fn draw_graphs(
+    history: Res<GraphHistory>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    mut gizmos: Gizmos,
+) {
+    let Ok((camera, transform)) = camera.get_single() else {
+        return;
+    };
+
+    let Some(viewport) = camera.logical_viewport_size() else {
+        return;
+    };
+
+    // Charts are laid out in viewport pixels, y pointing down
+    let resources_origin = Vec2::new(MARGIN, viewport.y - MARGIN);
+    let population_origin = resources_origin - Vec2::new(0., CHART_SIZE.y + MARGIN);
+
+    let to_world = |point: Vec2| camera.viewport_to_world_2d(transform, point);
+
+    let Some(last) = history.samples.back() else {
+        return;
+    };
+    // Step at the left edge of the charts
+    let first_step = last.statistics.step.saturating_sub(HISTORY_LEN - 1);
+
+    let population_max = POPULATION
+        .iter()
+        .flat_map(|series| {
+            history
+                .samples
+                .iter()
+                .map(|sample| (series.value)(&sample.statistics))
+        })
+        .fold(1., f64::max);
+
+    let charts = [
+        (population_origin, &POPULATION[..], Some(population_max)),
+        (resources_origin, &RESOURCES[..], None),
+    ];
+
+    for (origin, series, shared_max) in charts {
+        let (Some(bottom_left), Some(top_right)) = (
+            to_world(origin),
+            to_world(origin + Vec2::new(CHART_SIZE.x, -CHART_SIZE.y)),
+        ) else {
+            continue;
+        };
+
+        gizmos.rect_2d(
+            (bottom_left + top_right) / 2.,
+            0.,
+            top_right - bottom_left,
+            Color::DARK_GRAY,
+        );
+
+        for series in series {
+            // Series without a shared scale are normalized to their own maximum
+            let max = shared_max.unwrap_or_else(|| {
+                history
+                    .samples
+                    .iter()
+                    .map(|sample| (series.value)(&sample.statistics))
+                    .fold(f64::EPSILON, f64::max)
+            });
+
+            let mut line = Vec::new();
+
+            for sample in &history.samples {
+                if sample.after_gap && !line.is_empty() {
+                    gizmos.linestrip_2d(line.drain(..), series.color);
+                }
+
+                let x = (sample.statistics.step - first_step) as f32 / (HISTORY_LEN - 1) as f32
+                    * CHART_SIZE.x;
+                let y = ((series.value)(&sample.statistics) / max) as f32 * CHART_SIZE.y;
+
+                line.extend(to_world(origin + Vec2::new(x, -y)));
+            }
+
+            gizmos.linestrip_2d(line, series.color);
+        }
+    }
+}
+
+fn update_legend(history: Res<GraphHistory>, mut legend: Query<&mut Text, With<Legend>>) {
+    let Some(last) = history.samples.back() else {
+        return;
+    };
+    let last = &last.statistics;
+
+    for mut text in legend.iter_mut() {
+        text.sections = POPULATION
+            .iter()
+            .chain(RESOURCES.iter())
+            .map(|series| {
+                TextSection::new(
+                    format!("{}: {:.0}\n", series.name, (series.value)(last)),
+                    TextStyle {
+                        font_size: 12.,
+                        color: series.color,
+                        ..default()
+                    },
+                )
+            })
+            .collect();
+    }
+}
+
+fn update_legend_visibility(
+    simulation: Res<Simulation>,
+    mut legend: Query<&mut Visibility, With<Legend>>,
+) {
+    for mut visibility in legend.iter_mut() {
+        *visibility = if simulation.state.graphs_visible {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
@@ -0,0 +1,200 @@
+use bevy::prelude::*;
+
+use crate::{
+    cells::life_cell::{genome::Genome, EnergyDirections, LifeCell, LifeType},
+    simulation::Simulation,
+    types::Coord,
+};
+
+/// Shows the cell under the cursor
+#[derive(Default)]
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, startup)
+            .add_systems(Update, update_inspector);
+    }
+}
+
+#[derive(Component)]
+struct InspectorText;
+
+const FONT_SIZE: f32 = 12.;
+
+fn startup(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::default()
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(8.),
+                right: Val::Px(8.),
+                padding: UiRect::all(Val::Px(4.)),
+                ..default()
+            })
+            .with_background_color(Color::rgba(0., 0., 0., 0.7)),
+        InspectorText,
+    ));
+}
+
+fn update_inspector(
+    simulation: Res<Simulation>,
+    mut last_cursor: Local<Option<Coord>>,
+    mut inspector: Query<(&mut Text, &mut Visibility), With<InspectorText>>,
+) {
+    let state = &simulation.state;
+
+    // Cursor movement bypasses change detection, so track it separately
+    if !simulation.is_changed() && *last_cursor == Some(state.cursor_position) {
+        return;
+    }
+
+    *last_cursor = Some(state.cursor_position);
+
+    for (mut text, mut visibility) in inspector.iter_mut() {
+        if !state.inspector_visible {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+
+        *visibility = Visibility::Visible;
+
+        let Coord { x, y } = state.cursor_position;
+        text.sections = cell_sections(&simulation, x, y);
+    }
+}
+
+fn cell_sections(simulation: &Simulation, x: u32, y: u32) -> Vec<TextSection> {
+    let cell = simulation.world.cell(x, y);
+
+    let mut info = format!(
+        "Cell ({x}, {y})\n\
+         Soil organics: {}\n\
+         Soil energy: {:.2}\n\
+         Air pollution: {}\n",
+        cell.soil.organics, cell.soil.energy, cell.air.pollution
+    );
+
+    let LifeCell::Alive(life) = cell.life else {
+        info.push_str("Life: Dead\n");
+        return vec![section(info, Color::WHITE)];
+    };
+
+    info.push_str(&format!(
+        "Life: {}\n\
+         Organism: {}\n\
+         Energy: {:.2}\n\
+         Steps to death: {}\n\
+         Parent: {}\n\
+         Energy to: {}\n",
+        life.ty.name(),
+        life.organism.0,
+        life.energy,
+        life.steps_to_death,
+        life.parent_dir
+            .map_or(String::from("none"), |dir| format!("{dir:?}")),
+        energy_directions(&life.energy_to),
+    ));
+
+    let mut sections = vec![section(info, Color::WHITE)];
+
+    if let LifeType::Stem(genome) = life.ty {
+        genome_sections(simulation.genomes.get(genome), &mut sections);
+    }
+
+    sections
+}
+
+fn genome_sections(genome: &Genome, sections: &mut Vec<TextSection>) {
+    sections.push(section(
+        format!(
+            "\nGenome: mutation rate {}%, seed gene {}, active gene {}\n\
+             Lineage: {}, parent lineage: {}, hash: {:016x}\n",
+            genome.mutation_rate.0,
+            genome.seed_gene.0,
+            genome.active_gene.0,
+            genome.lineage.0,
+            genome
+                .parent_lineage
+                .map_or(String::from("none"), |lineage| lineage.0.to_string()),
+            genome.content_hash(),
+        ),
+        Color::WHITE,
+    ));
+
+    for (i, gene) in genome.genes.iter().enumerate() {
+        let color = if i == genome.active_gene.0 as usize {
+            Color::YELLOW
+        } else {
+            Color::GRAY
+        };
+
+        sections.push(section(
+            format!(
+                "{i:02} up: {:?} down: {:?} left: {:?} right: {:?}\n",
+                gene.up, gene.down, gene.left, gene.right
+            ),
+            color,
+        ));
+    }
+
+    let gene = genome.active_gene();
+
+    sections.push(section(
+        format!(
+            "\nActive gene\n\
+             Main: {:?} if {:?}({})\n\
+             Additional: {:?} / {:?} / {:?} if {:?}({}), {:?}({})\n\
+             Switch: {} / {} / {} if {:?}({}), {:?}({})\n\
+             Lifespan: {}",
+            gene.main_action,
+            gene.main_action_condition,
+            gene.main_action_param,
+            gene.additional_action1,
+            gene.additional_action2,
+            gene.additional_action3,
+            gene.additional_action_condition1,
+            gene.additional_action_param1,
+            gene.additional_action_condition2,
+            gene.additional_action_param2,
+            gene.alt_gene1.0,
+            gene.alt_gene2.0,
+            gene.alt_gene3.0,
+            gene.condition_1,
+            gene.param_1,
+            gene.condition_2,
+            gene.param_2,
+            gene.self_lifespan.0,
+        ),
+        Color::YELLOW,
+    ));
+}
+
+fn energy_directions(directions: &EnergyDirections) -> String {
+    let names: Vec<&str> = [
+        (directions.up, "up"),
+        (directions.down, "down"),
+        (directions.left, "left"),
+        (directions.right, "right"),
+    ]
+    .into_iter()
+    .filter_map(|(enabled, name)| enabled.then_some(name))
+    .collect();
+
+    if names.is_empty() {
+        String::from("none")
+    } else {
+        names.join(", ")
+    }
+}
+
+fn section(value: String, color: Color) -> TextSection {
+    TextSection::new(
+        value,
+        TextStyle {
+            font_size: FONT_SIZE,
+            color,
+            ..default()
+        },
+    )
+}
@@ -1,2 +1,6 @@
 pub mod control;
+pub mod graphs;
+pub mod inspector;
+pub mod statistics;
+pub mod ui;
 pub mod world;
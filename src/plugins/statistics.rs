@@ -0,0 +1,75 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use bevy::prelude::*;
+
+use crate::{simulation::Simulation, statistics::StepStatistics};
+
+use super::world::next_step;
+
+/// Writes [`StepStatistics`] to a CSV file every `interval` steps
+pub struct StatisticsPlugin {
+    pub path: Option<PathBuf>,
+    pub interval: usize,
+}
+
+impl Plugin for StatisticsPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let mut writer = match File::create(path) {
+            Ok(file) => BufWriter::new(file),
+            Err(err) => return error!("Failed to create {}: {err}", path.display()),
+        };
+
+        if let Err(err) = writeln!(writer, "{}", StepStatistics::CSV_HEADER) {
+            return error!("Failed to write {}: {err}", path.display());
+        }
+
+        app.insert_resource(StatisticsCsv {
+            writer,
+            interval: self.interval.max(1),
+            last_step: None,
+        })
+        .add_systems(
+            Update,
+            write_statistics
+                .after(next_step)
+                .run_if(resource_changed::<Simulation>),
+        );
+    }
+}
+
+#[derive(Resource)]
+struct StatisticsCsv {
+    writer: BufWriter<File>,
+    interval: usize,
+    last_step: Option<usize>,
+}
+
+fn write_statistics(simulation: Res<Simulation>, mut csv: ResMut<StatisticsCsv>) {
+    let step = simulation.state.simulation_step;
+
+    if !simulation.state.initialized
+        || !step.is_multiple_of(csv.interval)
+        || csv.last_step == Some(step)
+    {
+        return;
+    }
+
+    csv.last_step = Some(step);
+
+    let stats = StepStatistics::collect(&simulation);
+
+    if let Err(err) = stats
+        .write_csv_row(&mut csv.writer)
+        .and_then(|()| csv.writer.flush())
+    {
+        error!("Failed to write statistics: {err}");
+    }
+}
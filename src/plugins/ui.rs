@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+
+use crate::simulation::Simulation;
+
+use super::control::{BrushTool, StampTool};
+
+#[derive(Default)]
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, startup).add_systems(
+            Update,
+            update_status.run_if(
+                resource_changed::<Simulation>
+                    .or_else(resource_changed::<StampTool>)
+                    .or_else(resource_changed::<BrushTool>),
+            ),
+        );
+    }
+}
+
+#[derive(Component)]
+struct StatusText;
+
+fn startup(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.),
+            left: Val::Px(8.),
+            ..default()
+        }),
+        StatusText,
+    ));
+}
+
+fn update_status(
+    simulation: Res<Simulation>,
+    stamp: Res<StampTool>,
+    brush: Res<BrushTool>,
+    mut text: Query<&mut Text, With<StatusText>>,
+) {
+    for mut text in text.iter_mut() {
+        text.sections[0].value = format!(
+            "Seed: {}\nStep: {}",
+            simulation.settings.seed, simulation.state.simulation_step
+        );
+
+        let light = &simulation.settings.light;
+        if light.day_length != 0 || light.year_length != 0 {
+            let top = light.light(simulation.state.simulation_step, 0, simulation.settings.h);
+            text.sections[0].value += &format!("\nSunlight: {top:.2}");
+        }
+
+        if stamp.active {
+            let genome = stamp.genome.map_or(String::from("none"), |genome| {
+                format!("{:016x}", genome.content_hash())
+            });
+
+            text.sections[0].value += &format!("\nStamp: genome {genome}, energy {}", stamp.energy);
+        }
+
+        if let Some(layer) = brush.layer {
+            text.sections[0].value += &format!(
+                "\nBrush: {}, radius {}, strength {}",
+                layer.name(),
+                brush.radius,
+                brush.strength
+            );
+        }
+    }
+}
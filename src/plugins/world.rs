@@ -1,19 +1,15 @@
-use crate::cells::{
-    life_cell::{AliveCell, EnergyDirections, LifeCell, LifeType::*},
-    soil_cell::MAX_ENERGY_LIFE,
-    WorldCell,
-};
-use crate::grid::{Area, Grid};
-use crate::types::{Settings, State};
-use crate::update::update_world;
-use crate::utils::get_map;
+use crate::cells::terrain_cell::WALL_TEXTURE_ID;
+use crate::climate::ClimateSchedule;
+use crate::simulation::Simulation;
+use crate::types::{Coord, Settings};
 use bevy::math::{uvec2, vec2, vec3};
 use bevy::prelude::*;
-use bevy_fast_tilemap::{FastTileMapPlugin, Map, MapBundleManaged};
-use rand::seq::SliceRandom;
+use bevy_fast_tilemap::{FastTileMapPlugin, Map, MapBundleManaged, MapIndexer};
 
-#[derive(Default)]
-pub struct WorldPlugin;
+pub struct WorldPlugin {
+    pub settings: Settings,
+    pub climate: ClimateSchedule,
+}
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
@@ -22,12 +18,20 @@ impl Plugin for WorldPlugin {
             .add_plugins(FastTileMapPlugin::default())
             // Systems
             .add_systems(Startup, startup)
-            .add_systems(Update, next_step.run_if(not_paused))
-            .add_systems(Update, initialize.run_if(not_initialized))
+            .add_systems(
+                Update,
+                (
+                    initialize.run_if(not_initialized),
+                    next_step.run_if(not_paused),
+                    sync_maps.run_if(resource_changed::<Simulation>),
+                )
+                    .chain(),
+            )
             // Resources
-            .insert_resource(Grid::<WorldCell>::default())
-            .insert_resource(Settings { w: 256, h: 256 })
-            .insert_resource(State::default());
+            .insert_resource(Simulation {
+                climate: self.climate.clone(),
+                ..Simulation::new(self.settings)
+            });
     }
 }
 
@@ -35,12 +39,11 @@ fn startup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut materials: ResMut<Assets<Map>>,
-    mut world: ResMut<Grid<WorldCell>>,
-    settings: Res<Settings>,
+    simulation: Res<Simulation>,
 ) {
     commands.spawn(Camera2dBundle::default());
 
-    *world = Grid::<WorldCell>::new(settings.w, settings.h);
+    let settings = simulation.settings;
 
     let cell_map = Map::builder(
         uvec2(settings.w, settings.h),
@@ -112,101 +115,114 @@ fn startup(
     });
 }
 
-fn initialize(
-    mut world: ResMut<Grid<WorldCell>>,
-    settings: Res<Settings>,
-    mut state: ResMut<State>,
-) {
-    for x in 0..settings.w {
-        for y in 0..settings.h {
-            let cell = world.get_mut(x as i64, y as i64);
-            *cell = WorldCell::default();
-
-            if x % 4 == 0 && y % 4 == 0 {
-                let life_cell = AliveCell::new(
-                    Stem(rand::random()),
-                    100.,
-                    EnergyDirections::default(),
-                    None,
-                    2,
-                );
-                cell.life = LifeCell::Alive(life_cell);
-            }
-        }
-    }
+fn initialize(mut simulation: ResMut<Simulation>) {
+    simulation.initialize();
+}
 
-    state.initialized = true;
+fn not_paused(simulation: Res<Simulation>) -> bool {
+    !simulation.state.paused
 }
 
-fn not_paused(state: Res<State>) -> bool {
-    !state.paused
+fn not_initialized(simulation: Res<Simulation>) -> bool {
+    !simulation.state.initialized
 }
 
-fn not_initialized(state: Res<State>) -> bool {
-    !state.initialized
+pub fn next_step(mut simulation: ResMut<Simulation>) {
+    simulation.step();
 }
 
-pub fn next_step(
+/// Write the current world into the tilemaps
+fn sync_maps(
     mut map_materials: ResMut<Assets<Map>>,
     maps: Query<&Handle<Map>>,
-    mut world: ResMut<Grid<WorldCell>>,
-    settings: Res<Settings>,
-    mut state: ResMut<State>,
+    simulation: Res<Simulation>,
 ) {
-    let mut organics_map = get_map(&maps, &mut *map_materials, 0);
-    let mut life_map = get_map(&maps, &mut *map_materials, 1);
-    let mut pollution_map = get_map(&maps, &mut *map_materials, 2);
-    let mut soil_energy_map = get_map(&maps, &mut *map_materials, 3);
-    let mut energy_directions_map = get_map(&maps, &mut *map_materials, 4);
-
-    let mut rng = rand::thread_rng();
+    let state = &simulation.state;
+    let textures: Vec<_> = cell_textures(&simulation).collect();
+
+    let visible = [
+        state.organic_visible,
+        state.life_visible,
+        state.pollution_visible,
+        true,
+        true,
+    ];
+
+    // One map is borrowed at a time, they all live in the same `Assets<Map>`
+    for (id, visible) in visible.into_iter().enumerate() {
+        if !visible {
+            continue;
+        }
 
-    let mut cell_order_x: Vec<u32> = (0..settings.w).collect();
-    cell_order_x.shuffle(&mut rng);
+        let mut map = get_map(&maps, &mut map_materials, id);
 
-    let mut cell_order_y: Vec<u32> = (0..settings.h).collect();
-    cell_order_y.shuffle(&mut rng);
+        for (Coord { x, y }, textures) in &textures {
+            let texture = textures.by_map()[id];
 
-    for x in &cell_order_x {
-        for y in &cell_order_y {
-            let mut area = Area::new(&mut *world, *x, *y);
-            update_world(&mut state, &mut area);
+            if map.at(*x, *y) != texture {
+                map.set(*x, *y, texture);
+            }
         }
     }
+}
 
-    for x in 0..settings.w {
-        for y in 0..settings.h {
-            let area = Area::new(&mut *world, x, y);
-
-            let organics_texture = area.center.soil.organics as u32;
-            let life_texture = area.center.life.texture_id(&area);
-            let pollution_texture = area.center.air.pollution as u32;
-            let soil_energy_texture =
-                ((area.center.soil.energy * 255. / MAX_ENERGY_LIFE) as u32).min(255);
-
-            let energy_directions_texturue = area.center.life.energy_directions_texture_id();
-
-            if organics_map.at(x, y) != organics_texture && state.organic_visible {
-                organics_map.set(x, y, organics_texture);
-            }
+fn get_map<'a>(
+    maps: &Query<&Handle<Map>>,
+    map_materials: &'a mut Assets<Map>,
+    id: usize,
+) -> MapIndexer<'a> {
+    let map_handle = maps.iter().nth(id).unwrap();
 
-            if life_map.at(x, y) != life_texture && state.life_visible {
-                life_map.set(x, y, life_texture);
-            }
+    let Some(map) = map_materials.get_mut(map_handle) else {
+        panic!("No map material");
+    };
 
-            if pollution_map.at(x, y) != pollution_texture && state.pollution_visible {
-                pollution_map.set(x, y, pollution_texture);
-            }
+    map.indexer_mut()
+}
 
-            if soil_energy_map.at(x, y) != soil_energy_texture {
-                soil_energy_map.set(x, y, soil_energy_texture);
-            }
+/// Tile of every map for one cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellTextures {
+    pub organics: u32,
+    pub life: u32,
+    pub pollution: u32,
+    pub soil_energy: u32,
+    pub energy_directions: u32,
+}
 
-            if energy_directions_map.at(x, y) != energy_directions_texturue {
-                energy_directions_map.set(x, y, energy_directions_texturue);
-            }
-        }
+impl CellTextures {
+    /// Tiles in the order the maps are spawned in `startup`
+    pub const fn by_map(&self) -> [u32; 5] {
+        [
+            self.organics,
+            self.life,
+            self.pollution,
+            self.soil_energy,
+            self.energy_directions,
+        ]
     }
+}
 
-    state.simulation_step += 1;
+/// Tiles of every cell row by row, the part of [`sync_maps`] that does not touch the GPU
+pub fn cell_textures(simulation: &Simulation) -> impl Iterator<Item = (Coord, CellTextures)> + '_ {
+    let world = &simulation.world;
+    let settings = &simulation.settings;
+
+    world.enumerate_cells().map(move |(coord, cell)| {
+        let life = if cell.terrain.is_wall() {
+            WALL_TEXTURE_ID
+        } else {
+            cell.life.texture_id(&world.life, coord.x, coord.y)
+        };
+
+        let textures = CellTextures {
+            organics: cell.soil.organics as u32,
+            life,
+            pollution: cell.air.pollution as u32,
+            soil_energy: ((cell.soil.energy * 255. / settings.max_energy_life) as u32).min(255),
+            energy_directions: cell.life.energy_directions_texture_id(),
+        };
+
+        (coord, textures)
+    })
 }
@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+
+use rand::{Rng, SeedableRng};
+
+use crate::{
+    cells::life_cell::{
+        genome::{arena::GenomeArena, Genome},
+        AliveCell, EnergyDirections, LifeCell,
+        LifeType::*,
+        OrganismId,
+    },
+    climate::ClimateSchedule,
+    diffusion::diffuse,
+    phylogeny::Phylogeny,
+    types::{Coord, IdAllocator, Settings, SimRng, State, UpdateMode},
+    update::{update_world_buffered, update_world_chunked, update_world_sequential},
+    world::World,
+};
+
+/// Simulation core, independent from rendering
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "render", derive(bevy::prelude::Resource))]
+pub struct Simulation {
+    pub world: World,
+    /// Genomes of the stem cells in `world`
+    pub genomes: GenomeArena,
+    /// Current settings, changed by climate events
+    pub settings: Settings,
+    /// Settings before any climate event, restored by [`Simulation::initialize`]
+    pub base_settings: Settings,
+    pub state: State,
+
+    pub rng: SimRng,
+
+    pub phylogeny: Phylogeny,
+
+    pub climate: ClimateSchedule,
+}
+
+impl Simulation {
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            world: World::new(settings.w, settings.h, settings.boundary),
+            genomes: GenomeArena::default(),
+            settings,
+            base_settings: settings,
+            state: State::default(),
+            rng: SimRng::seed_from_u64(settings.seed),
+            phylogeny: Phylogeny::default(),
+            climate: ClimateSchedule::default(),
+        }
+    }
+
+    /// Reset the world and seed it with random stem cells
+    pub fn initialize(&mut self) {
+        // Climate events replay from the start, on the settings they started from
+        self.settings = self.base_settings;
+        self.rng = SimRng::seed_from_u64(self.settings.seed);
+        self.state.ids = IdAllocator::default();
+
+        let spacing = self.settings.seed_spacing.max(1);
+
+        // Terrain is part of the experiment setup and survives resets
+        let terrain = std::mem::take(&mut self.world.terrain);
+        self.world = World {
+            terrain,
+            ..World::new(self.settings.w, self.settings.h, self.settings.boundary)
+        };
+        self.genomes.clear();
+
+        for x in (0..self.settings.w).step_by(spacing as usize) {
+            for y in (0..self.settings.h).step_by(spacing as usize) {
+                if !self.world.terrain.uget(x, y).is_wall() {
+                    let genome = self.rng.gen();
+                    self.place_stem(Coord { x, y }, genome, 100.);
+                }
+            }
+        }
+
+        self.state.simulation_step = 0;
+        self.state.initialized = true;
+
+        self.phylogeny.clear();
+        self.record_phylogeny();
+    }
+
+    /// Replace life at `coord` with a new organism grown from `genome`, starting a new lineage.
+    /// Returns `false` if `coord` is a wall.
+    pub fn place_stem(&mut self, coord: Coord, mut genome: Genome, energy: f32) -> bool {
+        if self.world.terrain.uget(coord.x, coord.y).is_wall() {
+            return false;
+        }
+
+        genome.active_gene = genome.seed_gene;
+        genome.lineage = self.state.ids.lineage();
+        genome.parent_lineage = None;
+
+        let id = self.genomes.insert(genome);
+        self.genomes.acquire(id);
+
+        if let Some(replaced) = self.world.life.uget(coord.x, coord.y).genome() {
+            self.genomes.release(replaced);
+        }
+
+        let life_cell = AliveCell::new(
+            Stem(id),
+            self.state.ids.organism(),
+            energy,
+            EnergyDirections::default(),
+            None,
+            2,
+        );
+
+        self.world
+            .life
+            .uset(coord.x, coord.y, LifeCell::Alive(life_cell));
+
+        true
+    }
+
+    /// Advance the world by one step
+    pub fn step(&mut self) {
+        self.climate.apply(
+            self.state.simulation_step,
+            &mut self.settings,
+            &mut self.rng,
+            &mut self.world,
+            &mut self.genomes,
+        );
+
+        diffuse(&self.settings, &mut self.rng, &mut self.world);
+
+        let update = match self.settings.update {
+            UpdateMode::Sequential => update_world_sequential,
+            UpdateMode::Buffered => update_world_buffered,
+            UpdateMode::Chunked => update_world_chunked,
+        };
+
+        update(
+            &self.settings,
+            &mut self.state,
+            &mut self.rng,
+            &mut self.world,
+            &mut self.genomes,
+        );
+
+        self.state.simulation_step += 1;
+
+        self.record_phylogeny();
+    }
+
+    fn record_phylogeny(&mut self) {
+        if self.settings.record_phylogeny {
+            self.phylogeny
+                .census(&self.genomes, self.state.simulation_step);
+        }
+    }
+
+    /// Amount of alive cells in every organism
+    pub fn organism_sizes(&self) -> HashMap<OrganismId, usize> {
+        let mut sizes = HashMap::new();
+
+        for cell in self.world.life.iter() {
+            if let LifeCell::Alive(life) = cell {
+                *sizes.entry(life.organism).or_default() += 1;
+            }
+        }
+
+        sizes
+    }
+
+    /// Genome of the organism at `coord`: the stem itself, or the nearest stem of the same
+    /// organism for its other cells
+    pub fn organism_genome(&self, coord: Coord) -> Option<Genome> {
+        let LifeCell::Alive(life) = self.world.life.uget(coord.x, coord.y) else {
+            return None;
+        };
+
+        if let Stem(genome) = life.ty {
+            return Some(*self.genomes.get(genome));
+        }
+
+        let mut nearest = None;
+
+        for (at, cell) in self.world.life.enumerate_coords() {
+            if let LifeCell::Alive(AliveCell {
+                ty: Stem(genome),
+                organism,
+                ..
+            }) = cell
+            {
+                if *organism != life.organism {
+                    continue;
+                }
+
+                let distance = at.x.abs_diff(coord.x) + at.y.abs_diff(coord.y);
+                if nearest.is_none_or(|(nearest, _)| distance < nearest) {
+                    nearest = Some((distance, *genome));
+                }
+            }
+        }
+
+        nearest.map(|(_, genome)| *self.genomes.get(genome))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cells::life_cell::genome::arena::GenomeId;
+
+    use super::*;
+
+    fn run(settings: Settings, steps: usize) -> Simulation {
+        let mut simulation = Simulation::new(settings);
+        simulation.initialize();
+
+        for _ in 0..steps {
+            simulation.step();
+        }
+
+        simulation
+    }
+
+    fn settings(update: UpdateMode) -> Settings {
+        Settings {
+            w: 32,
+            h: 32,
+            seed: 7,
+            update,
+            ..Settings::default()
+        }
+    }
+
+    /// Whole world as text, to compare runs exactly
+    fn world(simulation: &Simulation) -> String {
+        format!("{:?}", simulation.world)
+    }
+
+    #[test]
+    fn same_seed_same_result() {
+        for update in [
+            UpdateMode::Sequential,
+            UpdateMode::Buffered,
+            UpdateMode::Chunked,
+        ] {
+            assert_eq!(
+                world(&run(settings(update), 100)),
+                world(&run(settings(update), 100)),
+                "{update:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn chunked_does_not_depend_on_threads() {
+        let run = |threads| {
+            run(
+                Settings {
+                    threads,
+                    chunk_size: 8,
+                    ..settings(UpdateMode::Chunked)
+                },
+                100,
+            )
+        };
+
+        assert_eq!(world(&run(1)), world(&run(4)));
+    }
+
+    #[test]
+    fn genome_refs_count_stems() {
+        for update in [
+            UpdateMode::Sequential,
+            UpdateMode::Buffered,
+            UpdateMode::Chunked,
+        ] {
+            let simulation = run(settings(update), 100);
+
+            let mut stems: HashMap<GenomeId, u32> = HashMap::new();
+            for cell in simulation.world.life.iter() {
+                if let Some(id) = cell.genome() {
+                    *stems.entry(id).or_default() += 1;
+                }
+            }
+
+            let refs: HashMap<GenomeId, u32> = simulation
+                .genomes
+                .iter()
+                .map(|(id, _, refs)| (id, refs))
+                .collect();
+
+            assert_eq!(refs, stems, "{update:?}");
+        }
+    }
+
+    #[test]
+    fn initialize_replays_the_run() {
+        let mut simulation = run(settings(UpdateMode::Sequential), 50);
+
+        simulation.initialize();
+        for _ in 0..100 {
+            simulation.step();
+        }
+
+        assert_eq!(
+            world(&simulation),
+            world(&run(settings(UpdateMode::Sequential), 100))
+        );
+    }
+    #[test]
+    fn initialize_replays_the_climate() {
+        use crate::climate::{ClimateEvent, ClimateParameter, ScheduledEvent};
+
+        let climate = ClimateSchedule {
+            events: vec![
+                ScheduledEvent {
+                    at: 10,
+                    every: None,
+                    event: ClimateEvent::Set {
+                        parameter: ClimateParameter::LightIntensity,
+                        value: 0.3,
+                    },
+                },
+                ScheduledEvent {
+                    at: 20,
+                    every: None,
+                    event: ClimateEvent::Ramp {
+                        parameter: ClimateParameter::PollutionDecay,
+                        from: 0.5,
+                        to: 0.9,
+                        duration: 20,
+                    },
+                },
+            ],
+        };
+
+        let run = |steps| {
+            let mut simulation = Simulation {
+                climate: climate.clone(),
+                ..Simulation::new(settings(UpdateMode::Sequential))
+            };
+            simulation.initialize();
+
+            for _ in 0..steps {
+                simulation.step();
+            }
+
+            simulation
+        };
+
+        let mut simulation = run(50);
+        assert_eq!(simulation.settings.light.intensity, 0.3);
+
+        simulation.initialize();
+        assert_eq!(
+            simulation.settings.light.intensity,
+            Settings::default().light.intensity
+        );
+
+        for _ in 0..5 {
+            simulation.step();
+        }
+        assert_eq!(world(&simulation), world(&run(5)));
+    }
+}
@@ -0,0 +1,327 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cells::life_cell::{
+        genome::arena::{GenomeArena, GenomeId},
+        LifeCell, LifeType,
+    },
+    climate::ClimateSchedule,
+    grid::Grid,
+    phylogeny::Phylogeny,
+    simulation::Simulation,
+    types::{BoundaryMode, IdAllocator, Settings, SimRng, State},
+    world::World,
+};
+
+/// Magic bytes at the start of every snapshot file
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"SPLF";
+
+/// Bumped on every incompatible change of the snapshot layout
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Default path used by quick save and quick load
+pub const QUICKSAVE_PATH: &str = "quicksave.splf";
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    world: World,
+    genomes: GenomeArena,
+    settings: Settings,
+    simulation_step: usize,
+    ids: IdAllocator,
+    rng: SimRng,
+    phylogeny: Phylogeny,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Encoding(bincode::Error),
+    BadMagic,
+    UnsupportedVersion(u32),
+    /// Decoded fine but does not describe a consistent world
+    Invalid(String),
+}
+
+impl Snapshot {
+    /// Check what decoding alone does not: layer sizes and edges and genome references
+    fn validate(&self) -> Result<(), SnapshotError> {
+        fn layout<T: Default + Clone>(grid: &Grid<T>) -> (u32, u32, usize, BoundaryMode) {
+            (grid.width(), grid.height(), grid.len(), grid.boundary())
+        }
+
+        let (w, h, boundary) = (self.settings.w, self.settings.h, self.settings.boundary);
+        let world = &self.world;
+
+        let layers = [
+            ("life", layout(&world.life)),
+            ("organics", layout(&world.organics)),
+            ("soil energy", layout(&world.soil_energy)),
+            ("pollution", layout(&world.pollution)),
+            ("terrain", layout(&world.terrain)),
+        ];
+
+        for (name, (width, height, len, edges)) in layers {
+            if (width, height) != (w, h) {
+                return Err(SnapshotError::Invalid(format!(
+                    "{name} layer is {width}x{height}, settings are {w}x{h}"
+                )));
+            }
+
+            if len != w as usize * h as usize {
+                return Err(SnapshotError::Invalid(format!(
+                    "{name} layer has {len} cells, expected {}",
+                    w as usize * h as usize
+                )));
+            }
+
+            if edges != boundary {
+                return Err(SnapshotError::Invalid(format!(
+                    "{name} layer has {edges:?} edges, settings have {boundary:?}"
+                )));
+            }
+        }
+
+        let mut stems: HashMap<GenomeId, u32> = HashMap::new();
+
+        for (coord, cell) in world.life.enumerate_coords() {
+            if let LifeCell::Alive(alive) = cell {
+                if let LifeType::Stem(id) = alive.ty {
+                    if !self.genomes.contains(id) {
+                        return Err(SnapshotError::Invalid(format!(
+                            "stem at {}, {} uses missing genome {}",
+                            coord.x, coord.y, id.0
+                        )));
+                    }
+
+                    *stems.entry(id).or_default() += 1;
+                }
+            }
+        }
+
+        for (id, _, refs) in self.genomes.iter() {
+            let used = stems.get(&id).copied().unwrap_or_default();
+
+            if refs != used {
+                return Err(SnapshotError::Invalid(format!(
+                    "genome {} has {refs} references but {used} stems use it",
+                    id.0
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "io error: {err}"),
+            Self::Encoding(err) => write!(f, "encoding error: {err}"),
+            Self::BadMagic => write!(f, "not a snapshot file"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported snapshot version {version}, expected {SNAPSHOT_VERSION}"
+            ),
+            Self::Invalid(reason) => write!(f, "invalid snapshot: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<bincode::Error> for SnapshotError {
+    fn from(err: bincode::Error) -> Self {
+        Self::Encoding(err)
+    }
+}
+
+impl Simulation {
+    /// Write the whole simulation to `writer`
+    pub fn save_to<W: Write>(&self, mut writer: W) -> Result<(), SnapshotError> {
+        writer.write_all(&SNAPSHOT_MAGIC)?;
+        writer.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+
+        let snapshot = Snapshot {
+            world: self.world.clone(),
+            genomes: self.genomes.clone(),
+            settings: self.settings,
+            simulation_step: self.state.simulation_step,
+            ids: self.state.ids,
+            rng: self.rng.clone(),
+            phylogeny: self.phylogeny.clone(),
+        };
+
+        bincode::serialize_into(&mut writer, &snapshot)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Read a simulation previously written by [`Simulation::save_to`]
+    pub fn load_from<R: Read>(mut reader: R) -> Result<Self, SnapshotError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+
+        if magic != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let snapshot: Snapshot = bincode::deserialize_from(reader)?;
+        snapshot.validate()?;
+
+        Ok(Self {
+            world: snapshot.world,
+            genomes: snapshot.genomes,
+            settings: snapshot.settings,
+            base_settings: snapshot.settings,
+            state: State {
+                initialized: true,
+                simulation_step: snapshot.simulation_step,
+                ids: snapshot.ids,
+                ..State::default()
+            },
+            rng: snapshot.rng,
+            phylogeny: snapshot.phylogeny,
+            // Schedules come from the command line, not from snapshots
+            climate: ClimateSchedule::default(),
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        self.save_to(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        Self::load_from(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::world::World;
+
+    use super::*;
+
+    fn simulation() -> Simulation {
+        let mut simulation = Simulation::new(Settings {
+            w: 32,
+            h: 32,
+            seed: 7,
+            ..Settings::default()
+        });
+        simulation.initialize();
+
+        for _ in 0..50 {
+            simulation.step();
+        }
+
+        simulation
+    }
+
+    fn save(simulation: &Simulation) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        simulation.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut simulation = simulation();
+        let bytes = save(&simulation);
+
+        let mut loaded = Simulation::load_from(&bytes[..]).unwrap();
+        assert_eq!(save(&loaded), bytes);
+
+        // The loaded world carries on exactly like the original
+        for _ in 0..50 {
+            simulation.step();
+            loaded.step();
+        }
+        assert_eq!(save(&loaded), save(&simulation));
+    }
+
+    #[test]
+    fn rejects_bad_header() {
+        let mut bytes = save(&simulation());
+
+        bytes[0] = b'X';
+        assert!(matches!(
+            Simulation::load_from(&bytes[..]),
+            Err(SnapshotError::BadMagic)
+        ));
+
+        bytes[0] = SNAPSHOT_MAGIC[0];
+        bytes[4..8].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            Simulation::load_from(&bytes[..]),
+            Err(SnapshotError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_inconsistent_world() {
+        let simulation = simulation();
+
+        let mut missing_genomes = simulation.clone();
+        missing_genomes.genomes.clear();
+        assert!(matches!(
+            Simulation::load_from(&save(&missing_genomes)[..]),
+            Err(SnapshotError::Invalid(_))
+        ));
+
+        let mut wrong_refs = simulation.clone();
+        let (id, _, _) = wrong_refs.genomes.iter().next().unwrap();
+        wrong_refs.genomes.acquire(id);
+        assert!(matches!(
+            Simulation::load_from(&save(&wrong_refs)[..]),
+            Err(SnapshotError::Invalid(_))
+        ));
+
+        let mut wrong_edges = simulation.clone();
+        wrong_edges.world.pollution = Grid::new(32, 32, BoundaryMode::Absorbing);
+        assert!(matches!(
+            Simulation::load_from(&save(&wrong_edges)[..]),
+            Err(SnapshotError::Invalid(_))
+        ));
+
+        let mut wrong_layer_size = simulation.clone();
+        wrong_layer_size.world.organics = Grid::new(32, 16, simulation.settings.boundary);
+        assert!(matches!(
+            Simulation::load_from(&save(&wrong_layer_size)[..]),
+            Err(SnapshotError::Invalid(_))
+        ));
+
+        let wrong_size = Simulation {
+            world: World::new(16, 32, simulation.settings.boundary),
+            ..simulation
+        };
+        assert!(matches!(
+            Simulation::load_from(&save(&wrong_size)[..]),
+            Err(SnapshotError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,95 @@
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cells::life_cell::{LifeCell, LifeType},
+    simulation::Simulation,
+};
+
+/// Ecological metrics of the world at one step
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct StepStatistics {
+    pub step: usize,
+
+    pub pipe: u32,
+    pub leaf: u32,
+    pub root: u32,
+    pub reactor: u32,
+    pub filter: u32,
+    pub stem: u32,
+
+    pub life_energy: f64,
+    pub soil_organics: u64,
+    pub soil_energy: f64,
+    pub total_pollution: u64,
+    pub mean_pollution: f64,
+}
+
+impl StepStatistics {
+    pub const CSV_HEADER: &'static str = "step,pipe,leaf,root,reactor,filter,stem,alive,\
+        life_energy,soil_organics,soil_energy,total_pollution,mean_pollution";
+
+    pub fn collect(simulation: &Simulation) -> Self {
+        let settings = &simulation.settings;
+
+        let mut stats = Self {
+            step: simulation.state.simulation_step,
+            ..Self::default()
+        };
+
+        let world = &simulation.world;
+
+        for cell in world.life.iter() {
+            if let LifeCell::Alive(life) = cell {
+                match life.ty {
+                    LifeType::Pipe => stats.pipe += 1,
+                    LifeType::Leaf => stats.leaf += 1,
+                    LifeType::Root => stats.root += 1,
+                    LifeType::Reactor => stats.reactor += 1,
+                    LifeType::Filter => stats.filter += 1,
+                    LifeType::Stem(_) => stats.stem += 1,
+                }
+
+                stats.life_energy += life.energy as f64;
+            }
+        }
+
+        stats.soil_organics = world.organics.iter().map(|&organics| organics as u64).sum();
+        stats.soil_energy = world.soil_energy.iter().map(|&energy| energy as f64).sum();
+        stats.total_pollution = world
+            .pollution
+            .iter()
+            .map(|&pollution| pollution as u64)
+            .sum();
+
+        let cells = settings.w as f64 * settings.h as f64;
+        stats.mean_pollution = stats.total_pollution as f64 / cells.max(1.);
+
+        stats
+    }
+
+    pub const fn alive(&self) -> u32 {
+        self.pipe + self.leaf + self.root + self.reactor + self.filter + self.stem
+    }
+
+    pub fn write_csv_row<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.step,
+            self.pipe,
+            self.leaf,
+            self.root,
+            self.reactor,
+            self.filter,
+            self.stem,
+            self.alive(),
+            self.life_energy,
+            self.soil_organics,
+            self.soil_energy,
+            self.total_pollution,
+            self.mean_pollution,
+        )
+    }
+}
@@ -1,10 +1,25 @@
-use bevy::prelude::Resource;
+use std::str::FromStr;
+
 use rand::{
     distributions::{Distribution, Standard},
     Rng,
 };
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cells::{
+        life_cell::{genome::LineageId, ConsumptionTable, OrganismId},
+        soil_cell::{MAX_ENERGY_LIFE, MAX_ORGANIC_LIFE},
+    },
+    diffusion::DiffusionSettings,
+    light::LightSettings,
+};
+
+/// Random number generator used by every random decision of the simulation
+pub type SimRng = ChaCha8Rng;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum CellDir {
     Up,
     Down,
@@ -34,7 +49,7 @@ impl Distribution<CellDir> for Standard {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub struct Coord {
     pub x: u32,
     pub y: u32,
@@ -46,13 +61,126 @@ impl Coord {
     }
 }
 
-#[derive(Debug, Clone, Copy, Resource)]
+/// What lies beyond the edges of the world
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoundaryMode {
+    /// Opposite edges are connected, the world is a torus
+    #[default]
+    Wrap,
+    /// Beyond the edge is a wall
+    Walls,
+    /// Beyond the edge is empty space that swallows whatever enters it
+    Absorbing,
+    /// Beyond the edge is a wall mirroring the soil and air of the edge cell,
+    /// so nothing leaves the world but sensors see no cliff
+    Reflective,
+}
+
+impl FromStr for BoundaryMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrap" => Ok(Self::Wrap),
+            "walls" => Ok(Self::Walls),
+            "absorbing" => Ok(Self::Absorbing),
+            "reflective" => Ok(Self::Reflective),
+            _ => Err(format!(
+                "invalid boundary mode `{s}`, expected wrap, walls, absorbing or reflective"
+            )),
+        }
+    }
+}
+
+/// How cells are updated within a step
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateMode {
+    /// Cells act one after another in shuffled order, each seeing what the previous ones did
+    #[default]
+    Sequential,
+    /// Cells act on the world as it was before the step and their changes are merged,
+    /// see [`crate::update::update_world_buffered`]
+    Buffered,
+    /// Cells act in place like [`UpdateMode::Sequential`], but chunk by chunk on several threads,
+    /// see [`crate::update::update_world_chunked`]
+    Chunked,
+}
+
+impl FromStr for UpdateMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sequential" => Ok(Self::Sequential),
+            "buffered" => Ok(Self::Buffered),
+            "chunked" => Ok(Self::Chunked),
+            _ => Err(format!(
+                "invalid update mode `{s}`, expected sequential, buffered or chunked"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     pub w: u32,
     pub h: u32,
+
+    pub seed: u64,
+
+    pub boundary: BoundaryMode,
+    pub update: UpdateMode,
+    /// Side of the chunks of [`UpdateMode::Chunked`]
+    pub chunk_size: u32,
+    /// Threads used by [`UpdateMode::Chunked`], `0` for one per core
+    pub threads: usize,
+
+    /// Soil organics above which life dies (except roots)
+    pub max_organic_life: u8,
+    /// Soil energy above which life dies (except reactors)
+    pub max_energy_life: f32,
+    pub consumption: ConsumptionTable,
+    pub light: LightSettings,
+    pub diffusion: DiffusionSettings,
+
+    /// Distance between stem cells placed on initialization
+    pub seed_spacing: u32,
+
+    /// Keep a history of every lineage, see [`crate::phylogeny::Phylogeny`]
+    pub record_phylogeny: bool,
+}
+
+impl Default for Settings {
+    /// 256x256 world with a random seed
+    fn default() -> Self {
+        Self {
+            w: 256,
+            h: 256,
+
+            seed: rand::random(),
+
+            boundary: BoundaryMode::default(),
+            update: UpdateMode::default(),
+            chunk_size: 32,
+            threads: 0,
+
+            max_organic_life: MAX_ORGANIC_LIFE,
+            max_energy_life: MAX_ENERGY_LIFE,
+            consumption: ConsumptionTable::default(),
+            light: LightSettings::default(),
+            diffusion: DiffusionSettings::default(),
+
+            seed_spacing: 4,
+
+            record_phylogeny: true,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Resource)]
+#[derive(Debug, Clone)]
 pub struct State {
     pub cursor_position: Coord,
     pub paused: bool,
@@ -62,7 +190,11 @@ pub struct State {
     pub life_visible: bool,
     pub pollution_visible: bool,
 
+    pub inspector_visible: bool,
+    pub graphs_visible: bool,
+
     pub simulation_step: usize,
+    pub ids: IdAllocator,
 }
 
 impl Default for State {
@@ -76,7 +208,71 @@ impl Default for State {
             life_visible: true,
             pollution_visible: true,
 
+            inspector_visible: true,
+            graphs_visible: false,
+
             simulation_step: 0,
+            ids: IdAllocator::default(),
+        }
+    }
+}
+
+/// Hands out unique organism and lineage ids, `0` is never allocated
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct IdAllocator {
+    next_organism: u64,
+    next_lineage: u64,
+
+    /// Distance between ids handed out, above one for the parts of [`IdAllocator::split`]
+    #[serde(skip, default = "one")]
+    stride: u64,
+}
+
+const fn one() -> u64 {
+    1
+}
+
+impl IdAllocator {
+    pub fn organism(&mut self) -> OrganismId {
+        let id = OrganismId(self.next_organism);
+        self.next_organism += self.stride;
+        id
+    }
+
+    pub fn lineage(&mut self) -> LineageId {
+        let id = LineageId(self.next_lineage);
+        self.next_lineage += self.stride;
+        id
+    }
+
+    /// Split into `parts` allocators handing out interleaved ids, so they can be used in
+    /// parallel without coordination. Continue with [`IdAllocator::join`] afterwards.
+    pub fn split(&self, parts: usize) -> Vec<IdAllocator> {
+        (0..parts as u64)
+            .map(|part| Self {
+                next_organism: self.next_organism + part * self.stride,
+                next_lineage: self.next_lineage + part * self.stride,
+                stride: self.stride * parts as u64,
+            })
+            .collect()
+    }
+
+    /// Continue after every id handed out by the parts of [`IdAllocator::split`]
+    pub fn join(&mut self, parts: impl IntoIterator<Item = IdAllocator>) {
+        for part in parts {
+            self.next_organism = self.next_organism.max(part.next_organism);
+            self.next_lineage = self.next_lineage.max(part.next_lineage);
+        }
+    }
+}
+
+impl Default for IdAllocator {
+    fn default() -> Self {
+        Self {
+            next_organism: 1,
+            next_lineage: 1,
+
+            stride: 1,
         }
     }
 }
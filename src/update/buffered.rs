@@ -0,0 +1,367 @@
+use rand::{Rng, SeedableRng};
+
+use crate::{
+    cells::life_cell::{genome::arena::GenomeArena, EnergyDirections, LifeCell},
+    types::{CellDir, Coord, Settings, SimRng, State},
+    world::{World, WorldNeighbourhood},
+};
+
+use super::update_world;
+
+/// Slot of the center cell in [`World::neighbourhood`]
+const CENTER: usize = 4;
+
+/// What the cells around a cell did to it during a buffered step
+#[derive(Debug, Clone, Copy, Default)]
+struct Changes {
+    soil_energy: f32,
+    organics: i32,
+    pollution: i32,
+
+    life_energy: f32,
+    steps_to_death: i32,
+    /// Whether a neighbour killed the cell
+    killed: bool,
+    /// Energy links cut by neighbours
+    cut: EnergyDirections,
+    /// Whether a neighbour cut the link to the parent
+    orphaned: bool,
+}
+
+/// Newborn cell a parent tried to grow into an empty cell
+struct Birth {
+    target: (u32, u32),
+    life: LifeCell,
+
+    parent: usize,
+    parent_energy: f32,
+    /// Direction from the parent to the newborn
+    dir: CellDir,
+}
+
+/// Neighbour a cell killed and took the energy of
+struct Kill {
+    target: usize,
+    energy: f32,
+
+    killer: usize,
+    killer_energy: f32,
+    /// Slot of the target in the killer's neighbourhood
+    slot: usize,
+}
+
+/// Update every alive cell against the world as it was before the step, so the result does not
+/// depend on the order cells are visited in.
+///
+/// Each cell runs [`update_world`] on a copy of its neighbourhood and what it did to the copy is
+/// merged back: changes to soil, air, energy and lifespans add up, links cut by any neighbour
+/// stay cut, and a cell's own life is decided by the cell alone. When several parents grow into
+/// the same empty cell, the one with the most energy before the step wins, ties going to the
+/// first direction in [`CellDir`] order. The others still pay for the birth but lose the link
+/// to the cell they tried to grow into, and the genome of a stem that is not born is released. Kills are resolved the same way: when several cells kill
+/// the same neighbour, only the winner keeps its energy, ties going to the first slot in
+/// [`World::neighbourhood`] order.
+///
+/// Every cell draws from its own random stream, seeded once per step from `rng`.
+pub fn update_world_buffered(
+    settings: &Settings,
+    state: &mut State,
+    rng: &mut SimRng,
+    world: &mut World,
+    arena: &mut GenomeArena,
+) {
+    let mut genomes = arena.batches(1).remove(0);
+
+    let seed: <SimRng as SeedableRng>::Seed = rng.gen();
+
+    let mut changes = vec![Changes::default(); world.life.len()];
+    let mut lives = Vec::new();
+    let mut births = Vec::new();
+    let mut kills = Vec::new();
+
+    for (Coord { x, y }, cell) in world.life.enumerate_coords() {
+        let LifeCell::Alive(parent) = cell else {
+            continue;
+        };
+
+        let before = world.neighbourhood(x, y);
+        let mut after = before;
+
+        let mut cell_rng = SimRng::from_seed(seed);
+        cell_rng.set_stream(world.life.index(x, y) as u64);
+
+        update_world(
+            settings,
+            state,
+            &mut cell_rng,
+            &mut genomes,
+            &mut WorldNeighbourhood::from_cells(&mut after, x, y),
+        );
+
+        lives.push(((x, y), after.life[CENTER]));
+
+        for slot in 0..9 {
+            let neighbour = world.life.resolve(
+                x as i64 + slot as i64 % 3 - 1,
+                y as i64 + slot as i64 / 3 - 1,
+            );
+
+            // Changes beyond a non-wrapping edge are dropped
+            let Some((nx, ny)) = neighbour else {
+                continue;
+            };
+
+            let target = world.life.index(nx, ny);
+            let change = &mut changes[target];
+
+            change.soil_energy += after.soil_energy[slot] - before.soil_energy[slot];
+            change.organics += after.organics[slot] as i32 - before.organics[slot] as i32;
+            change.pollution += after.pollution[slot] as i32 - before.pollution[slot] as i32;
+
+            match (before.life[slot], after.life[slot]) {
+                (LifeCell::Alive(before), LifeCell::Alive(after)) if slot != CENTER => {
+                    let killed = after.energy == 0.
+                        && after.steps_to_death == 0
+                        && (before.energy > 0. || before.steps_to_death > 0);
+
+                    if killed {
+                        kills.push(Kill {
+                            target,
+                            energy: before.energy,
+
+                            killer: world.life.index(x, y),
+                            killer_energy: parent.energy,
+                            slot,
+                        });
+                    } else {
+                        change.life_energy += after.energy - before.energy;
+                        change.steps_to_death +=
+                            after.steps_to_death as i32 - before.steps_to_death as i32;
+                    }
+
+                    change.cut.up |= before.energy_to.up && !after.energy_to.up;
+                    change.cut.down |= before.energy_to.down && !after.energy_to.down;
+                    change.cut.left |= before.energy_to.left && !after.energy_to.left;
+                    change.cut.right |= before.energy_to.right && !after.energy_to.right;
+
+                    change.orphaned |= before.parent_dir.is_some() && after.parent_dir.is_none();
+                }
+                (LifeCell::Dead, life @ LifeCell::Alive(_)) => {
+                    let Some(dir) = slot_dir(slot) else {
+                        continue;
+                    };
+
+                    births.push(Birth {
+                        target: (nx, ny),
+                        life,
+
+                        parent: world.life.index(x, y),
+                        parent_energy: parent.energy,
+                        dir,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for ((x, y), life) in lives {
+        world.life.uset(x, y, life);
+    }
+
+    let life = &world.life;
+    births.sort_by(|a, b| {
+        life.index(a.target.0, a.target.1)
+            .cmp(&life.index(b.target.0, b.target.1))
+            .then(b.parent_energy.total_cmp(&a.parent_energy))
+            .then((a.dir as u8).cmp(&(b.dir as u8)))
+    });
+
+    for (i, birth) in births.iter().enumerate() {
+        if i > 0 && births[i - 1].target == birth.target {
+            cut_link(&mut changes[birth.parent].cut, birth.dir);
+
+            if let Some(id) = birth.life.genome() {
+                genomes.release(id);
+            }
+        } else {
+            world.life.uset(birth.target.0, birth.target.1, birth.life);
+        }
+    }
+
+    arena.append([genomes.finish()]);
+
+    kills.sort_by(|a, b| {
+        a.target
+            .cmp(&b.target)
+            .then(b.killer_energy.total_cmp(&a.killer_energy))
+            .then(a.slot.cmp(&b.slot))
+    });
+
+    for (i, kill) in kills.iter().enumerate() {
+        if i > 0 && kills[i - 1].target == kill.target {
+            changes[kill.killer].life_energy -= kill.energy;
+        } else {
+            changes[kill.target].killed = true;
+        }
+    }
+
+    let layers = world
+        .life
+        .iter_mut()
+        .zip(world.soil_energy.iter_mut())
+        .zip(world.organics.iter_mut())
+        .zip(world.pollution.iter_mut());
+
+    for ((((life, soil_energy), organics), pollution), change) in layers.zip(changes) {
+        *soil_energy = (*soil_energy + change.soil_energy).max(0.);
+        *organics = (*organics as i32 + change.organics).clamp(0, 255) as u8;
+        *pollution = (*pollution as i32 + change.pollution).clamp(0, 255) as u8;
+
+        // Cells that died on their own or were just born take nothing from their neighbours
+        if let LifeCell::Alive(life) = life {
+            if change.killed {
+                life.energy = 0.;
+                life.steps_to_death = 0;
+            } else {
+                life.energy = (life.energy + change.life_energy).max(0.);
+                life.steps_to_death = (life.steps_to_death as i32 + change.steps_to_death)
+                    .clamp(0, u16::MAX as i32) as u16;
+            }
+
+            life.energy_to.up &= !change.cut.up;
+            life.energy_to.down &= !change.cut.down;
+            life.energy_to.left &= !change.cut.left;
+            life.energy_to.right &= !change.cut.right;
+
+            if change.orphaned {
+                life.parent_dir = None;
+            }
+        }
+    }
+}
+
+/// Direction from the center to an orthogonal neighbour slot
+const fn slot_dir(slot: usize) -> Option<CellDir> {
+    match slot {
+        1 => Some(CellDir::Up),
+        3 => Some(CellDir::Left),
+        5 => Some(CellDir::Right),
+        7 => Some(CellDir::Down),
+        _ => None,
+    }
+}
+
+fn cut_link(cut: &mut EnergyDirections, dir: CellDir) {
+    match dir {
+        CellDir::Up => cut.up = true,
+        CellDir::Down => cut.down = true,
+        CellDir::Left => cut.left = true,
+        CellDir::Right => cut.right = true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+
+    use crate::{
+        cells::life_cell::{
+            genome::{arena::GenomeId, Gene, GeneAction, GeneDirectionAction, Genome},
+            AliveCell, LifeType, OrganismId,
+        },
+        types::BoundaryMode,
+    };
+
+    use super::*;
+
+    /// Genome doing nothing but `direction` to every side
+    fn genome(rng: &mut SimRng, direction: GeneDirectionAction) -> Genome {
+        let genome: Genome = rng.gen();
+
+        Genome {
+            genes: genome.genes.map(|gene| Gene {
+                up: direction,
+                down: direction,
+                left: direction,
+                right: direction,
+
+                main_action: GeneAction::DoNothing,
+                additional_action1: GeneAction::DoNothing,
+                additional_action2: GeneAction::DoNothing,
+                additional_action3: GeneAction::DoNothing,
+                ..gene
+            }),
+            ..genome
+        }
+    }
+
+    fn stem(id: GenomeId, energy: f32) -> LifeCell {
+        LifeCell::Alive(AliveCell::new(
+            LifeType::Stem(id),
+            OrganismId(0),
+            energy,
+            EnergyDirections::default(),
+            None,
+            100,
+        ))
+    }
+
+    fn energy(world: &World, x: u32, y: u32) -> f32 {
+        match world.life.get(x as i64, y as i64) {
+            Some(LifeCell::Alive(life)) => life.energy,
+            _ => 0.,
+        }
+    }
+
+    /// Energy of the left attacker, the victim and the right attacker after one step
+    fn attack(left: bool, right: bool) -> (f32, f32, f32) {
+        let settings = Settings::default();
+        let mut rng = SimRng::seed_from_u64(7);
+
+        let mut arena = GenomeArena::default();
+        let killer = arena.insert(genome(&mut rng, GeneDirectionAction::KillCell));
+        let victim = arena.insert(genome(&mut rng, GeneDirectionAction::Nothing));
+
+        let mut world = World::new(5, 5, BoundaryMode::default());
+        let mut place = |x, y, id, energy| {
+            world.life.uset(x, y, stem(id, energy));
+            arena.acquire(id);
+        };
+
+        place(2, 2, victim, 50.);
+        if left {
+            place(1, 2, killer, 10.);
+        }
+        if right {
+            place(3, 2, killer, 20.);
+        }
+
+        update_world_buffered(
+            &settings,
+            &mut State::default(),
+            &mut rng,
+            &mut world,
+            &mut arena,
+        );
+
+        (
+            energy(&world, 1, 2),
+            energy(&world, 2, 2),
+            energy(&world, 3, 2),
+        )
+    }
+
+    #[test]
+    fn one_killer_takes_the_energy() {
+        let (alone, _, _) = attack(true, false);
+        let (_, _, alone_right) = attack(false, true);
+        let (left, victim, right) = attack(true, true);
+
+        assert_eq!(victim, 0.);
+        // The right attacker had more energy so it wins
+        assert_eq!(right, alone_right);
+        // The victim's energy is only counted once
+        assert!((left + right - (alone + alone_right - 50.)).abs() < 1e-3);
+    }
+}
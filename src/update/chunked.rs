@@ -0,0 +1,142 @@
+use std::{num::NonZeroUsize, ops::Range, thread};
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    cells::life_cell::genome::arena::{GenomeArena, GenomeBatch},
+    types::{Settings, SimRng, State},
+    world::{World, WorldChunk},
+};
+
+use super::{update_world, update_world_sequential};
+
+/// Chunk with everything needed to update it on its own thread
+struct Task<'a> {
+    chunk: WorldChunk<'a>,
+    state: State,
+    rng: SimRng,
+    genomes: GenomeBatch<'a>,
+}
+
+/// Update every cell in place, chunk by chunk on [`Settings::threads`] threads.
+///
+/// The world is split into an even number of chunks along each axis and updated in four
+/// checkerboard phases. Chunks of the same phase are a whole chunk apart, so the areas of their
+/// cells never overlap and the chunks can be updated at the same time. Within a chunk cells act
+/// in shuffled order. Every chunk draws from its own random stream and ids, so the result does
+/// not depend on the number of threads.
+///
+/// Worlds too small for two chunks along each axis are updated sequentially.
+pub fn update_world_chunked(
+    settings: &Settings,
+    state: &mut State,
+    rng: &mut SimRng,
+    world: &mut World,
+    arena: &mut GenomeArena,
+) {
+    let (Some(columns), Some(rows)) = (
+        chunk_ranges(settings.w, settings.chunk_size),
+        chunk_ranges(settings.h, settings.chunk_size),
+    ) else {
+        return update_world_sequential(settings, state, rng, world, arena);
+    };
+
+    let threads = match settings.threads {
+        0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        threads => threads,
+    };
+
+    let seed: <SimRng as SeedableRng>::Seed = rng.gen();
+    let mut stream = 0;
+
+    for phase in 0..4 {
+        let columns: Vec<_> = columns.iter().skip(phase % 2).step_by(2).cloned().collect();
+        let rows: Vec<_> = rows.iter().skip(phase / 2).step_by(2).cloned().collect();
+
+        let chunks = world.chunks_mut(&columns, &rows);
+        let ids = state.ids.split(chunks.len());
+        let genomes = arena.batches(chunks.len());
+
+        let mut tasks: Vec<Task> = chunks
+            .into_iter()
+            .zip(ids)
+            .zip(genomes)
+            .map(|((chunk, ids), genomes)| {
+                let mut rng = SimRng::from_seed(seed);
+                rng.set_stream(stream);
+                stream += 1;
+
+                Task {
+                    chunk,
+                    state: State {
+                        ids,
+                        ..state.clone()
+                    },
+                    rng,
+                    genomes,
+                }
+            })
+            .collect();
+
+        let per_thread = tasks.len().div_ceil(threads);
+
+        thread::scope(|scope| {
+            for batch in tasks.chunks_mut(per_thread) {
+                scope.spawn(|| {
+                    for task in batch {
+                        update_chunk(settings, task);
+                    }
+                });
+            }
+        });
+
+        state.ids.join(tasks.iter().map(|task| task.state.ids));
+
+        let genomes: Vec<_> = tasks
+            .into_iter()
+            .map(|task| task.genomes.finish())
+            .collect();
+        arena.append(genomes);
+    }
+}
+
+fn update_chunk(settings: &Settings, task: &mut Task) {
+    let mut cell_order_x: Vec<u32> = task.chunk.columns().collect();
+    cell_order_x.shuffle(&mut task.rng);
+
+    let mut cell_order_y: Vec<u32> = task.chunk.rows().collect();
+    cell_order_y.shuffle(&mut task.rng);
+
+    for x in &cell_order_x {
+        for y in &cell_order_y {
+            if !task.chunk.life(*x, *y).is_alive() {
+                continue;
+            }
+
+            let mut area = task.chunk.neighbourhood(*x, *y);
+            update_world(
+                settings,
+                &mut task.state,
+                &mut task.rng,
+                &mut task.genomes,
+                &mut area,
+            );
+        }
+    }
+}
+
+/// Split `0..n` into an even number of ranges about `size` long, at least two cells each.
+/// `None` when `n` is too short for two ranges.
+fn chunk_ranges(n: u32, size: u32) -> Option<Vec<Range<u32>>> {
+    if n < 4 {
+        return None;
+    }
+
+    let count = (n / size.max(2)).clamp(2, n / 2) & !1;
+
+    Some(
+        (0..count)
+            .map(|i| i * n / count..(i + 1) * n / count)
+            .collect(),
+    )
+}
@@ -1,44 +1,69 @@
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 use crate::{
     all_directions, cell_directions, cell_op_directions_enum, cell_op_directions_with_enum,
-    cells::{
-        life_cell::{
-            genome::{
-                GeneAction::*,
-                GeneCondition::{self, *},
-                GeneDirectionAction::*,
-                Genome,
-            },
-            AliveCell,
-            LifeCell::*,
-            LifeType::*,
+    cells::life_cell::{
+        genome::{
+            arena::{GenomeBatch, GenomeId},
+            GeneAction::*,
+            GeneCondition::{self, *},
+            GeneDirectionAction::*,
         },
-        soil_cell::{MAX_ENERGY_LIFE, MAX_ORGANIC_LIFE},
-        WorldCell,
+        AliveCell,
+        LifeCell::*,
+        LifeType::*,
     },
-    grid::Area,
     types::{
         CellDir::{self, *},
-        State,
+        Settings, SimRng, State,
     },
+    world::WorldNeighbourhood,
 };
 
-pub fn update_life(state: &mut State, area: &mut Area<WorldCell>) {
-    if let Alive(mut life) = area.center.life {
+pub fn update_life(
+    settings: &Settings,
+    state: &mut State,
+    rng: &mut SimRng,
+    genomes: &mut GenomeBatch,
+    area: &mut WorldNeighbourhood,
+) {
+    let genome = area.life.center.genome();
+
+    update_cell(settings, state, rng, genomes, area);
+
+    // The cell switched genes, grew into a pipe or died
+    let changed = area.life.center.genome();
+    if changed != genome {
+        if let Some(id) = changed {
+            genomes.acquire(id);
+        }
+        if let Some(id) = genome {
+            genomes.release(id);
+        }
+    }
+}
+
+fn update_cell(
+    settings: &Settings,
+    state: &mut State,
+    rng: &mut SimRng,
+    genomes: &mut GenomeBatch,
+    area: &mut WorldNeighbourhood,
+) {
+    if let Alive(mut life) = *area.life.center {
         if life.steps_to_death == 0 {
             return kill(area);
         } else {
             life.steps_to_death -= 1;
         }
 
-        if ((area.center.soil.organics > MAX_ORGANIC_LIFE) && (life.ty != Root))
-            || ((area.center.soil.energy > MAX_ENERGY_LIFE) && (life.ty != Reactor))
+        if ((*area.organics.center > settings.max_organic_life) && (life.ty != Root))
+            || ((*area.soil_energy.center > settings.max_energy_life) && (life.ty != Reactor))
         {
             return kill(area);
         }
 
-        life.energy -= life.consumption();
+        life.energy -= life.consumption(&settings.consumption);
 
         if life.energy < 0. {
             return kill(area);
@@ -56,45 +81,93 @@ pub fn update_life(state: &mut State, area: &mut Area<WorldCell>) {
             }
         }
 
-        generate_energy(area, &mut life);
+        generate_energy(settings, state, area, &mut life);
 
         // Transfer energy
-        transfer_energy(area, &mut life);
+        transfer_energy(settings, area, &mut life);
 
         // Process genome
-        match life.ty {
-            Stem(genome) => {
-                process_genome(state, area, &mut life, genome);
-            }
-            _ => {}
-        };
+        if let Stem(genome) = life.ty {
+            process_genome(settings, state, rng, genomes, area, &mut life, genome);
+        }
 
-        area.center.life = Alive(life);
+        *area.life.center = Alive(life);
     }
 }
 
 fn process_genome(
-    state: &State,
-    area: &mut Area<WorldCell>,
+    settings: &Settings,
+    state: &mut State,
+    rng: &mut SimRng,
+    genomes: &mut GenomeBatch,
+    area: &mut WorldNeighbourhood,
     life: &mut AliveCell,
-    mut genome: Genome,
+    id: GenomeId,
 ) {
+    let mut genome = *genomes.get(id);
+    // Id of `genome` while it still matches a stored one, children share it until it changes
+    let mut stored = Some(id);
+
     let total_energy = genome.active_gene().energy_capacity();
 
     if life.energy > total_energy {
         let mut birth_once = false;
 
+        macro_rules! genome_id {
+            () => {
+                *stored.get_or_insert_with(|| genomes.insert(genome))
+            };
+        }
+
+        macro_rules! set_active_gene {
+            ($gene: expr) => {{
+                let gene = $gene;
+
+                if genome.active_gene != gene {
+                    genome.active_gene = gene;
+                    stored = None;
+                }
+            }};
+        }
+
+        macro_rules! mutate {
+            () => {{
+                let before = genome;
+
+                if genome.mutate(rng) {
+                    genome.branch_lineage(state.ids.lineage());
+                }
+
+                if genome != before {
+                    stored = None;
+                }
+            }};
+        }
+
         macro_rules! try_birth {
-            ($dir: ident, $op_dir: ident, $cell_type: expr, $steps_to_death: expr) => {{
-                if let Alive(mut $dir) = area.$dir.life {
+            ($dir: ident, $op_dir: ident, $cell_type: expr, $steps_to_death: expr, $organism: expr) => {{
+                if area.terrain.$dir.is_wall() {
+                    // Nothing grows into walls
+                } else if let Alive(mut $dir) = *area.life.$dir {
                     $dir.steps_to_death = $dir.steps_to_death.saturating_sub(250);
-                    area.$dir.life = Alive($dir);
+                    *area.life.$dir = Alive($dir);
                 } else {
-                    if $cell_type.is_fertile() {
+                    let cell_type = $cell_type;
+
+                    if cell_type.is_fertile() {
                         life.energy_to.$dir = true;
                     }
 
-                    area.$dir.life = $cell_type.make_newborn_cell($op_dir, $steps_to_death);
+                    *area.life.$dir = cell_type.make_newborn_cell(
+                        $op_dir,
+                        $steps_to_death,
+                        $organism,
+                        &settings.consumption,
+                    );
+
+                    if let Some(id) = area.life.$dir.genome() {
+                        genomes.acquire(id);
+                    }
 
                     birth_once = true;
                 }
@@ -103,13 +176,13 @@ fn process_genome(
 
         macro_rules! kill_cell {
             ($dir:ident) => {
-                if let Alive(mut $dir) = area.$dir.life {
+                if let Alive(mut $dir) = *area.life.$dir {
                     life.energy += $dir.energy;
 
                     $dir.steps_to_death = 0;
                     $dir.energy = 0.0;
 
-                    area.$dir.life = Alive($dir);
+                    *area.life.$dir = Alive($dir);
                 }
             };
         }
@@ -117,21 +190,35 @@ fn process_genome(
         macro_rules! direction_action {
             ($dir: ident, $op_dir: ident) => {
                 match genome.active_gene().$dir {
-                    MakeLeaf(lifespan) => try_birth!($dir, $op_dir, Leaf, lifespan.0),
-                    MakeRoot(lifespan) => try_birth!($dir, $op_dir, Root, lifespan.0),
-                    MakeReactor(lifespan) => try_birth!($dir, $op_dir, Reactor, lifespan.0),
-                    MakeFilter(lifespan) => try_birth!($dir, $op_dir, Filter, lifespan.0),
+                    MakeLeaf(lifespan) => {
+                        try_birth!($dir, $op_dir, Leaf, lifespan.0, life.organism)
+                    }
+                    MakeRoot(lifespan) => {
+                        try_birth!($dir, $op_dir, Root, lifespan.0, life.organism)
+                    }
+                    MakeReactor(lifespan) => {
+                        try_birth!($dir, $op_dir, Reactor, lifespan.0, life.organism)
+                    }
+                    MakeFilter(lifespan) => {
+                        try_birth!($dir, $op_dir, Filter, lifespan.0, life.organism)
+                    }
                     MultiplySelf(lifespan, next_gene) => {
-                        genome.mutate();
-                        genome.active_gene = next_gene;
+                        mutate!();
+                        set_active_gene!(next_gene);
 
-                        try_birth!($dir, $op_dir, Stem(genome), lifespan.0);
+                        try_birth!($dir, $op_dir, Stem(genome_id!()), lifespan.0, life.organism);
                     }
                     CreateSeed(lifespan) => {
-                        genome.mutate();
-                        genome.active_gene = genome.seed_gene;
-
-                        try_birth!($dir, $op_dir, Stem(genome), lifespan.0);
+                        mutate!();
+                        set_active_gene!(genome.seed_gene);
+
+                        try_birth!(
+                            $dir,
+                            $op_dir,
+                            Stem(genome_id!()),
+                            lifespan.0,
+                            state.ids.organism()
+                        );
                     }
                     KillCell => kill_cell!($dir),
 
@@ -142,9 +229,11 @@ fn process_genome(
 
         macro_rules! move_organic {
             ($from: ident, $to: ident) => {{
-                let to_move = (255 - area.$to.soil.organics).min(area.$from.soil.organics);
-                area.$from.soil.organics -= to_move;
-                area.$to.soil.organics += to_move;
+                if !area.terrain.$from.is_wall() && !area.terrain.$to.is_wall() {
+                    let to_move = (255 - *area.organics.$to).min(*area.organics.$from);
+                    *area.organics.$from -= to_move;
+                    *area.organics.$to += to_move;
+                }
             }};
         }
 
@@ -164,8 +253,8 @@ fn process_genome(
                     DoNothing => {}
 
                     ChangeActiveGene(gene_location) => {
-                        genome.active_gene = gene_location;
-                        life.ty = Stem(genome);
+                        set_active_gene!(gene_location);
+                        life.ty = Stem(genome_id!());
                     }
 
                     KillUpLeft => kill_cell!(up_left),
@@ -180,7 +269,9 @@ fn process_genome(
         }
 
         if check_gene_condition(
+            settings,
             state,
+            rng,
             area,
             life,
             genome.active_gene().main_action_condition,
@@ -191,7 +282,9 @@ fn process_genome(
 
         {
             let condition_1 = check_gene_condition(
+                settings,
                 state,
+                rng,
                 area,
                 life,
                 genome.active_gene().additional_action_condition1,
@@ -199,7 +292,9 @@ fn process_genome(
             );
 
             let condition_2 = check_gene_condition(
+                settings,
                 state,
+                rng,
                 area,
                 life,
                 genome.active_gene().additional_action_condition2,
@@ -216,7 +311,9 @@ fn process_genome(
 
         {
             let condition_1 = check_gene_condition(
+                settings,
                 state,
+                rng,
                 area,
                 life,
                 genome.active_gene().condition_1,
@@ -224,7 +321,9 @@ fn process_genome(
             );
 
             let condition_2 = check_gene_condition(
+                settings,
                 state,
+                rng,
                 area,
                 life,
                 genome.active_gene().condition_2,
@@ -232,9 +331,9 @@ fn process_genome(
             );
 
             match (condition_1, condition_2) {
-                (true, true) => genome.active_gene = genome.active_gene().alt_gene1,
-                (true, false) => genome.active_gene = genome.active_gene().alt_gene2,
-                (false, true) => genome.active_gene = genome.active_gene().alt_gene3,
+                (true, true) => set_active_gene!(genome.active_gene().alt_gene1),
+                (true, false) => set_active_gene!(genome.active_gene().alt_gene2),
+                (false, true) => set_active_gene!(genome.active_gene().alt_gene3),
                 (false, false) => {}
             };
 
@@ -252,60 +351,89 @@ fn process_genome(
 }
 
 fn check_gene_condition(
+    settings: &Settings,
     state: &State,
-    area: &Area<WorldCell>,
+    rng: &mut SimRng,
+    area: &WorldNeighbourhood,
     life: &AliveCell,
     condition: GeneCondition,
     param: u8,
 ) -> bool {
     match condition {
-        LifeUp => area.up.life.is_alive(),
-        LifeDown => area.down.life.is_alive(),
-        LifeLeft => area.left.life.is_alive(),
-        LifeRight => area.right.life.is_alive(),
-
-        LethalOrganicUp => area.up.soil.organics > MAX_ORGANIC_LIFE,
-        LethalOrganicDown => area.down.soil.organics > MAX_ORGANIC_LIFE,
-        LethalOrganicLeft => area.left.soil.organics > MAX_ORGANIC_LIFE,
-        LethalOrganicRight => area.right.soil.organics > MAX_ORGANIC_LIFE,
-
-        LethalEnergyUp => area.up.soil.energy > MAX_ENERGY_LIFE,
-        LethalEnergyDown => area.down.soil.energy > MAX_ENERGY_LIFE,
-        LethalEnergyLeft => area.left.soil.energy > MAX_ENERGY_LIFE,
-        LethalEnergyRight => area.right.soil.energy > MAX_ENERGY_LIFE,
-
-        RandomMT => thread_rng().gen::<u8>() > param,
+        LifeUp => area.life.up.is_alive(),
+        LifeDown => area.life.down.is_alive(),
+        LifeLeft => area.life.left.is_alive(),
+        LifeRight => area.life.right.is_alive(),
+
+        LethalOrganicUp => *area.organics.up > settings.max_organic_life,
+        LethalOrganicDown => *area.organics.down > settings.max_organic_life,
+        LethalOrganicLeft => *area.organics.left > settings.max_organic_life,
+        LethalOrganicRight => *area.organics.right > settings.max_organic_life,
+
+        LethalEnergyUp => *area.soil_energy.up > settings.max_energy_life,
+        LethalEnergyDown => *area.soil_energy.down > settings.max_energy_life,
+        LethalEnergyLeft => *area.soil_energy.left > settings.max_energy_life,
+        LethalEnergyRight => *area.soil_energy.right > settings.max_energy_life,
+
+        RandomMT => rng.gen::<u8>() > param,
         LifeEnergyMT => life.energy > param as f32,
 
-        OrganicCenterMT => area.center.soil.organics > param,
-        OrganicUpMT => area.up.soil.organics > param,
-        OrganicDownMT => area.down.soil.organics > param,
-        OrganicLeftMT => area.left.soil.organics > param,
-        OrganicRightMT => area.right.soil.organics > param,
+        OrganicCenterMT => *area.organics.center > param,
+        OrganicUpMT => *area.organics.up > param,
+        OrganicDownMT => *area.organics.down > param,
+        OrganicLeftMT => *area.organics.left > param,
+        OrganicRightMT => *area.organics.right > param,
 
-        SoilEnergyCenterMT => area.center.soil.energy > param as f32,
-        SoilEnergyUpMT => area.up.soil.energy > param as f32,
-        SoilEnergyDownMT => area.down.soil.energy > param as f32,
-        SoilEnergyLeftMT => area.left.soil.energy > param as f32,
-        SoilEnergyRightMT => area.right.soil.energy > param as f32,
+        SoilEnergyCenterMT => *area.soil_energy.center > param as f32,
+        SoilEnergyUpMT => *area.soil_energy.up > param as f32,
+        SoilEnergyDownMT => *area.soil_energy.down > param as f32,
+        SoilEnergyLeftMT => *area.soil_energy.left > param as f32,
+        SoilEnergyRightMT => *area.soil_energy.right > param as f32,
 
-        AirPollutionCenterMT => area.center.air.pollution > param,
-        AirPollutionUpMT => area.up.air.pollution > param,
-        AirPollutionDownMT => area.down.air.pollution > param,
-        AirPollutionLeftMT => area.left.air.pollution > param,
-        AirPollutionRightMT => area.right.air.pollution > param,
+        AirPollutionCenterMT => *area.pollution.center > param,
+        AirPollutionUpMT => *area.pollution.up > param,
+        AirPollutionDownMT => *area.pollution.down > param,
+        AirPollutionLeftMT => *area.pollution.left > param,
+        AirPollutionRightMT => *area.pollution.right > param,
 
         Always => true,
         Never => false,
 
-        StepsDividesP => state.simulation_step % param.max(1) as usize == 0,
+        StepsDividesP => state.simulation_step.is_multiple_of(param.max(1) as usize),
     }
 }
 
-fn generate_energy(area: &mut Area<WorldCell>, life: &mut AliveCell) {
+/// Add the energy a leaf, root, reactor or filter makes from its surroundings to `life`
+pub fn generate_energy(
+    settings: &Settings,
+    state: &State,
+    area: &mut WorldNeighbourhood,
+    life: &mut AliveCell,
+) {
     match life.ty {
         Leaf => {
-            let total = 1.2 / (area.center.air.pollution as f32 / 4.).max(1.);
+            let mut neighbours = 0;
+
+            macro_rules! count_neighbour {
+                ($dir: ident) => {
+                    if area.life.$dir.is_alive() {
+                        neighbours += 1;
+                    }
+                };
+            }
+
+            cell_directions!(count_neighbour);
+            count_neighbour!(up_left);
+            count_neighbour!(up_right);
+            count_neighbour!(down_left);
+            count_neighbour!(down_right);
+
+            let light = settings
+                .light
+                .light(state.simulation_step, area.y, settings.h)
+                * settings.light.shade(neighbours);
+
+            let total = light / (*area.pollution.center as f32 / 4.).max(1.);
             life.energy += total;
         }
         Root => {
@@ -313,12 +441,12 @@ fn generate_energy(area: &mut Area<WorldCell>, life: &mut AliveCell) {
 
             macro_rules! process_organic {
                 ($dir: ident) => {
-                    if area.$dir.soil.organics <= 8 && area.$dir.soil.organics > 0 {
-                        area.$dir.soil.organics -= 1;
+                    if *area.organics.$dir <= 8 && *area.organics.$dir > 0 {
+                        *area.organics.$dir -= 1;
                         total += 1.;
                     } else {
-                        let organic = (area.$dir.soil.organics as f32 * 0.16) as u8;
-                        area.$dir.soil.organics -= organic;
+                        let organic = (*area.organics.$dir as f32 * 0.16) as u8;
+                        *area.organics.$dir -= organic;
 
                         total += organic as f32;
                     };
@@ -328,20 +456,16 @@ fn generate_energy(area: &mut Area<WorldCell>, life: &mut AliveCell) {
             all_directions!(process_organic);
 
             life.energy += total * 0.6;
-            area.center.soil.energy += total * 0.2;
-            area.center.air.pollution = area
-                .center
-                .air
-                .pollution
-                .saturating_add((total * 0.5) as u8);
+            *area.soil_energy.center += total * 0.2;
+            *area.pollution.center = area.pollution.center.saturating_add((total * 0.5) as u8);
         }
         Reactor => {
             let mut total = 0.0;
 
             macro_rules! process_energy {
                 ($dir: ident) => {
-                    let energy = area.$dir.soil.energy * 0.16;
-                    area.$dir.soil.energy -= energy;
+                    let energy = *area.soil_energy.$dir * 0.16;
+                    *area.soil_energy.$dir -= energy;
 
                     total += energy as f32;
                 };
@@ -356,12 +480,12 @@ fn generate_energy(area: &mut Area<WorldCell>, life: &mut AliveCell) {
 
             macro_rules! process_pollution {
                 ($dir: ident) => {
-                    if area.$dir.air.pollution <= 8 && area.$dir.air.pollution > 0 {
-                        area.$dir.air.pollution -= 1;
+                    if *area.pollution.$dir <= 8 && *area.pollution.$dir > 0 {
+                        *area.pollution.$dir -= 1;
                         total += 1.;
                     } else {
-                        let pollution = (area.$dir.air.pollution as f32 * 0.16);
-                        area.$dir.air.pollution -= pollution as u8;
+                        let pollution = (*area.pollution.$dir as f32 * 0.16);
+                        *area.pollution.$dir -= pollution as u8;
 
                         total += pollution;
                     };
@@ -376,14 +500,14 @@ fn generate_energy(area: &mut Area<WorldCell>, life: &mut AliveCell) {
     }
 }
 
-fn reroute_energy_paths(area: &mut Area<WorldCell>, life: &mut AliveCell, parent_dir: CellDir) {
+fn reroute_energy_paths(area: &mut WorldNeighbourhood, life: &mut AliveCell, parent_dir: CellDir) {
     macro_rules! reroute {
         ($dir: ident, $op_dir: ident) => {
-            if let Alive(mut up) = area.$dir.life {
+            if let Alive(mut up) = *area.life.$dir {
                 life.energy_to.$dir = true;
 
                 up.energy_to.$op_dir = false;
-                area.$dir.life = Alive(up);
+                *area.life.$dir = Alive(up);
             }
         };
     }
@@ -397,7 +521,7 @@ fn reroute_energy_paths(area: &mut Area<WorldCell>, life: &mut AliveCell, parent
 }
 
 /// Transfer energy
-fn transfer_energy(area: &mut Area<WorldCell>, life: &mut AliveCell) {
+fn transfer_energy(settings: &Settings, area: &mut WorldNeighbourhood, life: &mut AliveCell) {
     if !life.can_transfer() || life.energy_to.branches_amount() == 0 {
         return;
     }
@@ -406,7 +530,7 @@ fn transfer_energy(area: &mut Area<WorldCell>, life: &mut AliveCell) {
         let to_flow = if life.steps_to_death == 1 {
             life.energy
         } else {
-            (life.energy - 1.1 * life.consumption()).max(0.)
+            (life.energy - 1.1 * life.consumption(&settings.consumption)).max(0.)
         };
 
         life.energy -= to_flow;
@@ -417,10 +541,10 @@ fn transfer_energy(area: &mut Area<WorldCell>, life: &mut AliveCell) {
     macro_rules! transfer {
         ($dir: ident) => {
             if life.energy_to.$dir {
-                if let Alive(mut $dir) = area.$dir.life {
+                if let Alive(mut $dir) = *area.life.$dir {
                     if $dir.is_pipe_recipient() {
                         $dir.energy += flow_each;
-                        area.$dir.life = Alive($dir);
+                        *area.life.$dir = Alive($dir);
                     } else {
                         life.energy_to.$dir = false;
                     }
@@ -435,35 +559,33 @@ fn transfer_energy(area: &mut Area<WorldCell>, life: &mut AliveCell) {
 }
 
 /// Kill cell and reroute energy paths
-fn kill(area: &mut Area<WorldCell>) {
-    area.center.soil.organics = area
-        .center
-        .soil
+pub fn kill(area: &mut WorldNeighbourhood) {
+    *area.organics.center = area
         .organics
-        .saturating_add(area.center.life.organics());
+        .center
+        .saturating_add(area.life.center.organics());
 
-    area.center.soil.energy += area.center.life.energy() * 0.5;
+    *area.soil_energy.center += area.life.center.energy() * 0.5;
 
-    area.center.air.pollution = area
-        .center
-        .air
+    *area.pollution.center = area
         .pollution
-        .saturating_add((area.center.life.organics() / 2).max(1));
+        .center
+        .saturating_add((area.life.center.organics() / 2).max(1));
 
-    area.center.life = Dead;
+    *area.life.center = Dead;
 
     // Reroute energy of neighbors
     {
         macro_rules! reroute {
             ($dir:ident,$op_dir:ident, $op_dir_enum: ident) => {
-                if let Alive(mut $dir) = area.$dir.life {
+                if let Alive(mut $dir) = *area.life.$dir {
                     $dir.energy_to.$op_dir = false;
 
                     if let Some($op_dir_enum) = $dir.parent_dir {
                         $dir.parent_dir = None;
                     }
 
-                    area.$dir.life = Alive($dir);
+                    *area.life.$dir = Alive($dir);
                 }
             };
         }
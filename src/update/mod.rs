@@ -1,15 +1,58 @@
-use crate::{cells::WorldCell, grid::Area, types::State};
+use rand::seq::SliceRandom;
 
-mod air;
+use crate::{
+    cells::life_cell::genome::arena::{GenomeArena, GenomeBatch},
+    types::{Settings, SimRng, State},
+    world::{World, WorldNeighbourhood},
+};
+
+mod buffered;
+mod chunked;
 mod life;
-mod soil;
 
-use air::*;
+pub use buffered::update_world_buffered;
+pub use chunked::update_world_chunked;
+pub use life::{generate_energy, kill};
+
 use life::*;
-use soil::*;
 
-pub fn update_world(state: &mut State, area: &mut Area<WorldCell>) {
-    update_soil(area);
-    update_air(area);
-    update_life(state, area);
+pub fn update_world(
+    settings: &Settings,
+    state: &mut State,
+    rng: &mut SimRng,
+    genomes: &mut GenomeBatch,
+    area: &mut WorldNeighbourhood,
+) {
+    update_life(settings, state, rng, genomes, area);
+}
+
+/// Update every cell in place, one after another in shuffled order
+pub fn update_world_sequential(
+    settings: &Settings,
+    state: &mut State,
+    rng: &mut SimRng,
+    world: &mut World,
+    arena: &mut GenomeArena,
+) {
+    let mut genomes = arena.batches(1).remove(0);
+
+    let mut cell_order_x: Vec<u32> = (0..settings.w).collect();
+    cell_order_x.shuffle(rng);
+
+    let mut cell_order_y: Vec<u32> = (0..settings.h).collect();
+    cell_order_y.shuffle(rng);
+
+    for x in &cell_order_x {
+        for y in &cell_order_y {
+            if !world.life.uget(*x, *y).is_alive() {
+                continue;
+            }
+
+            let mut area = world.neighbourhood_mut(*x, *y);
+            update_world(settings, state, rng, &mut genomes, &mut area);
+        }
+    }
+
+    let genomes = genomes.finish();
+    arena.append([genomes]);
 }
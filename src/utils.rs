@@ -1,59 +1,48 @@
-use bevy::{
-    asset::{Assets, Handle},
-    prelude::Query,
-};
-use bevy_fast_tilemap::{Map, MapIndexer};
-
 use crate::{
-    cells::{
-        life_cell::{genome::LifeSpan, EnergyDirections, LifeCell::*},
-        WorldCell,
-    },
-    grid::Area,
+    cells::life_cell::{EnergyDirections, LifeCell},
+    grid::Grid,
+    types::BoundaryMode,
 };
 
 pub fn get_continual_coord(n: i64, max: u32) -> u32 {
     (n).rem_euclid(max as i64) as u32
 }
 
-pub fn get_map<'a>(
-    maps: &Query<&Handle<Map>>,
-    map_materials: *mut Assets<Map>,
-    id: usize,
-) -> MapIndexer<'a> {
-    let map_handle = maps.iter().nth(id).unwrap();
-    let map_materials = unsafe { &mut *map_materials };
-
-    let Some(map) = map_materials.get_mut(map_handle) else {
-        panic!("No map material");
-    };
-
-    map.indexer_mut()
+/// Coordinate `n` inside `0..max`, `None` if it lies beyond a non-wrapping edge
+pub fn get_boundary_coord(n: i64, max: u32, boundary: BoundaryMode) -> Option<u32> {
+    match boundary {
+        BoundaryMode::Wrap => Some(get_continual_coord(n, max)),
+        _ => (0..max as i64).contains(&n).then_some(n as u32),
+    }
 }
 
-pub const fn merge_energy(
-    area: &Area<WorldCell>,
+pub fn merge_energy(
+    life: &Grid<LifeCell>,
+    x: u32,
+    y: u32,
     mut directions: EnergyDirections,
 ) -> EnergyDirections {
-    if let Alive(life) = area.up.life {
+    let (x, y) = (x as i64, y as i64);
+
+    if let Some(LifeCell::Alive(life)) = life.get(x, y - 1) {
         if life.energy_to.down {
             directions.up = true
         }
     }
 
-    if let Alive(life) = area.down.life {
+    if let Some(LifeCell::Alive(life)) = life.get(x, y + 1) {
         if life.energy_to.up {
             directions.down = true
         }
     }
 
-    if let Alive(life) = area.left.life {
+    if let Some(LifeCell::Alive(life)) = life.get(x - 1, y) {
         if life.energy_to.right {
             directions.left = true
         }
     }
 
-    if let Alive(life) = area.right.life {
+    if let Some(LifeCell::Alive(life)) = life.get(x + 1, y) {
         if life.energy_to.left {
             directions.right = true
         }
@@ -62,28 +51,6 @@ pub const fn merge_energy(
     directions
 }
 
-#[macro_export]
-macro_rules! all_foreach_left {
-    ($area: ident, $layer: ident, $val: ident) => {{
-        let mut total: u16 = 0;
-
-        total += $area.up_left.$layer.$val as u16;
-        total += $area.up.$layer.$val as u16;
-        total += $area.up_right.$layer.$val as u16;
-        total += $area.left.$layer.$val as u16;
-        total += $area.center.$layer.$val as u16;
-        total += $area.right.$layer.$val as u16;
-        total += $area.down_left.$layer.$val as u16;
-        total += $area.down.$layer.$val as u16;
-        total += $area.down_right.$layer.$val as u16;
-
-        let foreach: u8 = (total / 9) as u8;
-        let left = (total - (foreach as u16 * 9)) as u8;
-
-        (foreach, left)
-    }};
-}
-
 #[macro_export]
 macro_rules! all_directions {
     ($macro: ident) => {
@@ -130,3 +97,29 @@ macro_rules! cell_op_directions_enum {
         $macro!(right, Left);
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boundary_coords() {
+        assert_eq!(get_boundary_coord(-1, 5, BoundaryMode::Wrap), Some(4));
+        assert_eq!(get_boundary_coord(0, 5, BoundaryMode::Wrap), Some(0));
+        assert_eq!(get_boundary_coord(4, 5, BoundaryMode::Wrap), Some(4));
+        assert_eq!(get_boundary_coord(5, 5, BoundaryMode::Wrap), Some(0));
+        assert_eq!(get_boundary_coord(-6, 5, BoundaryMode::Wrap), Some(4));
+        assert_eq!(get_boundary_coord(11, 5, BoundaryMode::Wrap), Some(1));
+
+        for boundary in [
+            BoundaryMode::Walls,
+            BoundaryMode::Absorbing,
+            BoundaryMode::Reflective,
+        ] {
+            assert_eq!(get_boundary_coord(-1, 5, boundary), None, "{boundary:?}");
+            assert_eq!(get_boundary_coord(0, 5, boundary), Some(0), "{boundary:?}");
+            assert_eq!(get_boundary_coord(4, 5, boundary), Some(4), "{boundary:?}");
+            assert_eq!(get_boundary_coord(5, 5, boundary), None, "{boundary:?}");
+        }
+    }
+}
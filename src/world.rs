@@ -0,0 +1,203 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cells::{
+        air_cell::AirCell, life_cell::LifeCell, soil_cell::SoilCell, terrain_cell::TerrainCell,
+        WorldCell,
+    },
+    grid::{Chunk, Grid, Neighbourhood},
+    types::{BoundaryMode, Coord},
+};
+
+/// The simulated world, one dense grid per layer so passes over a single layer stay cheap
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct World {
+    pub life: Grid<LifeCell>,
+    pub organics: Grid<u8>,
+    pub soil_energy: Grid<f32>,
+    pub pollution: Grid<u8>,
+    pub terrain: Grid<TerrainCell>,
+}
+
+impl World {
+    pub fn new(width: u32, height: u32, boundary: BoundaryMode) -> Self {
+        Self {
+            life: Grid::new(width, height, boundary),
+            organics: Grid::new(width, height, boundary),
+            soil_energy: Grid::new(width, height, boundary),
+            pollution: Grid::new(width, height, boundary),
+            terrain: Grid::new(width, height, boundary),
+        }
+    }
+
+    pub const fn width(&self) -> u32 {
+        self.life.width()
+    }
+
+    pub const fn height(&self) -> u32 {
+        self.life.height()
+    }
+
+    /// Every layer of the cell at `x`, `y`
+    pub fn cell(&self, x: u32, y: u32) -> WorldCell {
+        WorldCell {
+            life: *self.life.uget(x, y),
+            soil: SoilCell {
+                organics: *self.organics.uget(x, y),
+                energy: *self.soil_energy.uget(x, y),
+            },
+            air: AirCell {
+                pollution: *self.pollution.uget(x, y),
+            },
+            terrain: *self.terrain.uget(x, y),
+        }
+    }
+
+    /// Replace every layer of the cell at `x`, `y`
+    pub fn set_cell(&mut self, x: u32, y: u32, cell: WorldCell) {
+        self.life.uset(x, y, cell.life);
+        self.organics.uset(x, y, cell.soil.organics);
+        self.soil_energy.uset(x, y, cell.soil.energy);
+        self.pollution.uset(x, y, cell.air.pollution);
+        self.terrain.uset(x, y, cell.terrain);
+    }
+
+    /// Cells row by row together with their coordinates
+    pub fn enumerate_cells(&self) -> impl Iterator<Item = (Coord, WorldCell)> + '_ {
+        (0..self.height())
+            .flat_map(move |y| (0..self.width()).map(move |x| (Coord::new(x, y), self.cell(x, y))))
+    }
+
+    /// Neighbourhood of `x`, `y` in every layer, see [`Neighbourhood::new`]
+    pub fn neighbourhood_mut(&mut self, x: u32, y: u32) -> WorldNeighbourhood<'_> {
+        WorldNeighbourhood {
+            life: Neighbourhood::new(&mut self.life, x, y),
+            organics: Neighbourhood::new(&mut self.organics, x, y),
+            soil_energy: Neighbourhood::new(&mut self.soil_energy, x, y),
+            pollution: Neighbourhood::new(&mut self.pollution, x, y),
+            terrain: Neighbourhood::new(&mut self.terrain, x, y),
+
+            x,
+            y,
+        }
+    }
+
+    /// Copy of the neighbourhood of `x`, `y` in every layer, see [`Grid::neighbourhood`]
+    pub fn neighbourhood(&self, x: u32, y: u32) -> Neighbours {
+        Neighbours {
+            life: self.life.neighbourhood(x, y),
+            organics: self.organics.neighbourhood(x, y),
+            soil_energy: self.soil_energy.neighbourhood(x, y),
+            pollution: self.pollution.neighbourhood(x, y),
+            terrain: self.terrain.neighbourhood(x, y),
+        }
+    }
+
+    /// Chunks of every layer, see [`Grid::chunks_mut`]
+    pub fn chunks_mut(
+        &mut self,
+        columns: &[Range<u32>],
+        rows: &[Range<u32>],
+    ) -> Vec<WorldChunk<'_>> {
+        let life = self.life.chunks_mut(columns, rows);
+        let organics = self.organics.chunks_mut(columns, rows);
+        let soil_energy = self.soil_energy.chunks_mut(columns, rows);
+        let pollution = self.pollution.chunks_mut(columns, rows);
+        let terrain = self.terrain.chunks_mut(columns, rows);
+
+        life.into_iter()
+            .zip(organics)
+            .zip(soil_energy)
+            .zip(pollution)
+            .zip(terrain)
+            .map(
+                |((((life, organics), soil_energy), pollution), terrain)| WorldChunk {
+                    life,
+                    organics,
+                    soil_energy,
+                    pollution,
+                    terrain,
+                },
+            )
+            .collect()
+    }
+}
+
+/// A cell and its eight neighbours in every layer
+#[derive(Debug)]
+pub struct WorldNeighbourhood<'a> {
+    pub life: Neighbourhood<'a, LifeCell>,
+    pub organics: Neighbourhood<'a, u8>,
+    pub soil_energy: Neighbourhood<'a, f32>,
+    pub pollution: Neighbourhood<'a, u8>,
+    pub terrain: Neighbourhood<'a, TerrainCell>,
+
+    pub x: u32,
+    pub y: u32,
+}
+
+impl<'a> WorldNeighbourhood<'a> {
+    /// Neighbourhood of cells detached from the world, see [`Neighbourhood::from_cells`]
+    pub fn from_cells(cells: &'a mut Neighbours, x: u32, y: u32) -> Self {
+        Self {
+            life: Neighbourhood::from_cells(&mut cells.life, x, y),
+            organics: Neighbourhood::from_cells(&mut cells.organics, x, y),
+            soil_energy: Neighbourhood::from_cells(&mut cells.soil_energy, x, y),
+            pollution: Neighbourhood::from_cells(&mut cells.pollution, x, y),
+            terrain: Neighbourhood::from_cells(&mut cells.terrain, x, y),
+
+            x,
+            y,
+        }
+    }
+}
+
+/// Copy of a neighbourhood in every layer, in [`Neighbourhood::from_cells`] order
+#[derive(Debug, Clone, Copy)]
+pub struct Neighbours {
+    pub life: [LifeCell; 9],
+    pub organics: [u8; 9],
+    pub soil_energy: [f32; 9],
+    pub pollution: [u8; 9],
+    pub terrain: [TerrainCell; 9],
+}
+
+/// Rectangle of every layer borrowed together with a one cell border, see [`World::chunks_mut`]
+pub struct WorldChunk<'a> {
+    life: Chunk<'a, LifeCell>,
+    organics: Chunk<'a, u8>,
+    soil_energy: Chunk<'a, f32>,
+    pollution: Chunk<'a, u8>,
+    terrain: Chunk<'a, TerrainCell>,
+}
+
+impl WorldChunk<'_> {
+    pub fn columns(&self) -> Range<u32> {
+        self.life.columns.clone()
+    }
+
+    pub fn rows(&self) -> Range<u32> {
+        self.life.rows.clone()
+    }
+
+    /// Life at `x`, `y`, which must lie inside the chunk
+    pub fn life(&self, x: u32, y: u32) -> &LifeCell {
+        self.life.uget(x, y)
+    }
+
+    /// Neighbourhood of `x`, `y` in every layer, see [`Chunk::neighbourhood`]
+    pub fn neighbourhood(&mut self, x: u32, y: u32) -> WorldNeighbourhood<'_> {
+        WorldNeighbourhood {
+            life: self.life.neighbourhood(x, y),
+            organics: self.organics.neighbourhood(x, y),
+            soil_energy: self.soil_energy.neighbourhood(x, y),
+            pollution: self.pollution.neighbourhood(x, y),
+            terrain: self.terrain.neighbourhood(x, y),
+
+            x,
+            y,
+        }
+    }
+}